@@ -0,0 +1,115 @@
+//
+// basis.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// The search-space primitives used by the optimiser: a `SharedValue` is an aliasable handle
+// onto a single free parameter (a cell length, a site coordinate, ...), and a `Basis` pairs one
+// with the bounds the optimiser is allowed to sample it within.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use nalgebra::RealField;
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+/// A single free parameter, shared between the struct that owns it (e.g. `Cell2`,
+/// `OccupiedSite`) and the `Basis` entry the optimiser perturbs.
+///
+/// Cloning a `SharedValue` clones the handle, not the value, so updates made through a
+/// `Basis` are immediately visible to every other holder -- this is what lets
+/// `State::generate_basis` hand out a fresh `Vec<Basis>` each call while still mutating the
+/// live configuration in place.
+///
+/// `T` defaults to `f64` for the same reason as [`Shape`](crate::Shape), but unlike `Cell2` or
+/// the concrete shapes, `SharedValue` is only ever instantiated at that default in practice:
+/// search-space sampling (`Basis`) only makes sense at `f64`, since `f32`/SIMD instantiations
+/// are for the geometry hot path, not for driving the annealer directly.
+#[derive(Clone, Debug)]
+pub struct SharedValue<T: RealField = f64>(Rc<Cell<T>>);
+
+impl<T: RealField> SharedValue<T> {
+    pub fn new(value: T) -> Self {
+        SharedValue(Rc::new(Cell::new(value)))
+    }
+
+    pub fn get_value(&self) -> T {
+        self.0.get()
+    }
+
+    pub fn set_value(&self, value: T) {
+        self.0.set(value);
+    }
+}
+
+impl<T: RealField> PartialEq for SharedValue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_value() == other.get_value()
+    }
+}
+
+/// A sampleable parameter within the optimiser's search space.
+#[derive(Clone, Debug)]
+pub struct Basis {
+    value: SharedValue,
+    previous: Cell<f64>,
+    low: f64,
+    high: f64,
+}
+
+impl Basis {
+    pub fn new(value: &SharedValue, low: f64, high: f64) -> Self {
+        Basis {
+            value: value.clone(),
+            previous: Cell::new(value.get_value()),
+            low,
+            high,
+        }
+    }
+
+    pub fn get_value(&self) -> f64 {
+        self.value.get_value()
+    }
+
+    /// Replace the current value, remembering the previous one so it can be restored by
+    /// `reset_value` if the move is rejected.
+    pub fn set_value(&self, new_value: f64) {
+        self.previous.set(self.value.get_value());
+        self.value.set_value(new_value);
+    }
+
+    /// Undo the most recent `set_value`.
+    pub fn reset_value(&self) {
+        self.value.set_value(self.previous.get());
+    }
+
+    /// Draw a new candidate value uniformly from `[low, high)`.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        Uniform::new(self.low, self.high).sample(rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn shared_value_aliases() {
+        let value = SharedValue::new(1.);
+        let alias = value.clone();
+        alias.set_value(2.);
+        assert_abs_diff_eq!(value.get_value(), 2.);
+    }
+
+    #[test]
+    fn reset_restores_previous_value() {
+        let value = SharedValue::new(1.);
+        let basis = Basis::new(&value, 0., 10.);
+        basis.set_value(5.);
+        assert_abs_diff_eq!(value.get_value(), 5.);
+        basis.reset_value();
+        assert_abs_diff_eq!(value.get_value(), 1.);
+    }
+}
@@ -0,0 +1,165 @@
+//
+// packing.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// The command line front-end for the optimiser: runs `num_start_configs` replicas of
+// `monte_carlo_best_packing` in parallel via rayon, periodically checkpointing each replica's
+// best state so a crashed or interrupted run can be resumed, and emits the overall best
+// configuration as a structured JSON report.
+
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Error;
+use clap::{App, Arg};
+use crystal_packing::wallpaper::{Wallpaper, WallpaperGroup, WallpaperGroups, WyckoffSite};
+use crystal_packing::{
+    monte_carlo_best_packing, LineShape, MCVars, PackedState, PackingFraction, PackingResult,
+};
+use log::info;
+use rayon::prelude::*;
+
+/// The number of Monte Carlo steps run between successive checkpoint writes.
+const CHECKPOINT_INTERVAL: u64 = 1000;
+
+fn cli() -> clap::ArgMatches<'static> {
+    App::new("packing")
+        .version("0.1.0")
+        .author("Malcolm Ramsay <malramsay64@gmail.com")
+        .about("Find best tilings of 2d shapes")
+        .arg(
+            Arg::with_name("wallpaper_group")
+                .possible_values(&WallpaperGroups::variants())
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("sides")
+                .long("--num-sides")
+                .takes_value(true)
+                .default_value("4"),
+        )
+        .arg(
+            Arg::with_name("steps")
+                .short("-s")
+                .long("--steps")
+                .takes_value(true)
+                .default_value("100"),
+        )
+        .arg(
+            Arg::with_name("checkpoint")
+                .long("--checkpoint")
+                .takes_value(true)
+                .help("Directory in which to periodically write per-replica checkpoints and the final result"),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("--resume")
+                .requires("checkpoint")
+                .help("Resume each replica from the checkpoint written by a previous run, instead of starting from a fresh `initialise`"),
+        )
+        .get_matches()
+}
+
+/// Run a single replica for `vars.steps` total steps, writing a checkpoint to
+/// `checkpoint_dir/replica_<index>.json` every [`CHECKPOINT_INTERVAL`] steps so the replica can
+/// be resumed from its last saved configuration.
+fn run_replica(
+    index: usize,
+    shape: LineShape,
+    wallpaper: Wallpaper,
+    isopointal: &[WyckoffSite],
+    vars: &MCVars,
+    checkpoint_dir: Option<&Path>,
+    resume: bool,
+) -> Result<PackedState<LineShape>, Error> {
+    let checkpoint_path = checkpoint_dir.map(|dir| dir.join(format!("replica_{}.json", index)));
+
+    let mut state = match &checkpoint_path {
+        Some(path) if resume && path.exists() => {
+            info!("Replica {}: resuming from {}", index, path.display());
+            PackedState::load_checkpoint(path)?
+        }
+        _ => PackedState::initialise(shape, wallpaper, isopointal),
+    };
+
+    let mut steps_remaining = vars.steps;
+    while steps_remaining > 0 {
+        let steps_this_chunk = steps_remaining.min(CHECKPOINT_INTERVAL);
+        let chunk_vars = MCVars {
+            steps: steps_this_chunk,
+            ..vars.clone()
+        };
+
+        let PackingResult { state: next, .. } =
+            monte_carlo_best_packing::<_, PackingFraction>(&chunk_vars, state)?;
+        state = next;
+        steps_remaining -= steps_this_chunk;
+
+        if let Some(path) = &checkpoint_path {
+            state.save_checkpoint(path)?;
+        }
+    }
+    Ok(state)
+}
+
+fn main() -> Result<(), Error> {
+    env_logger::init();
+    let matches = cli();
+
+    let num_sides: usize = matches.value_of("sides").unwrap().parse().unwrap();
+    let polygon = LineShape::from_radial("Polygon", vec![1.; num_sides])?;
+
+    let wallpaper_group = WallpaperGroups::from_str(matches.value_of("wallpaper_group").unwrap())?;
+    println!("Using Wallpaper Group: {}", wallpaper_group);
+    let group: WallpaperGroup = wallpaper_group.try_into()?;
+
+    let wallpaper = Wallpaper::new(&group);
+    let isopointal = &[WyckoffSite::new(&group)?];
+
+    let mut vars = MCVars::default();
+    vars.steps = matches.value_of("steps").unwrap().parse().unwrap();
+    vars.num_start_configs = 32;
+
+    let checkpoint_dir = matches.value_of("checkpoint").map(PathBuf::from);
+    if let Some(dir) = &checkpoint_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let resume = matches.is_present("resume");
+
+    let best = (0..vars.num_start_configs)
+        .into_par_iter()
+        .map(|i| {
+            run_replica(
+                i as usize,
+                polygon.clone(),
+                wallpaper.clone(),
+                isopointal,
+                &vars,
+                checkpoint_dir.as_deref(),
+                resume,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .max()
+        .unwrap();
+
+    let report = best.to_report();
+    match &checkpoint_dir {
+        Some(dir) => {
+            let final_path = dir.join("final.json");
+            let file = std::fs::File::create(&final_path)?;
+            serde_json::to_writer_pretty(file, &report)?;
+            println!("Wrote final result to {}", final_path.display());
+        }
+        None => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    println!(
+        "Final packing fraction: {}",
+        report.packing_fraction.unwrap_or(0.)
+    );
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//
+// cell.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// The unit cell the packing is tiled within. Generic over the scalar type `T`, defaulting to
+// `f64`, for the same reason as `Shape` (see its doc comment in `traits.rs`).
+
+use std::f64::consts::PI;
+
+use nalgebra::{Matrix2, RealField, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::basis::{Basis, SharedValue};
+use crate::transform::Transform2;
+use crate::CrystalFamily;
+
+/// The unit cell of the 2D tiling: two side lengths and the angle between them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub struct Cell2<T: RealField = f64> {
+    x_len: T,
+    y_len: T,
+    angle: T,
+    family: CrystalFamily,
+}
+
+impl<T: RealField> Cell2<T> {
+    pub fn a(&self) -> T {
+        self.x_len.clone()
+    }
+
+    pub fn b(&self) -> T {
+        self.y_len.clone()
+    }
+
+    pub fn angle(&self) -> T {
+        self.angle.clone()
+    }
+
+    pub fn area(&self) -> T {
+        self.angle.clone().sin() * self.x_len.clone() * self.y_len.clone()
+    }
+
+    /// The basis matrix whose columns are the cell's lattice vectors.
+    fn basis_matrix(&self) -> Matrix2<T> {
+        Matrix2::new(
+            self.x_len.clone(),
+            self.y_len.clone() * self.angle.clone().cos(),
+            T::zero(),
+            self.y_len.clone() * self.angle.clone().sin(),
+        )
+    }
+
+    /// Convert a fractional-coordinate transform into a Cartesian one.
+    pub fn to_cartesian_isometry(&self, fractional: Transform2<T>) -> Transform2<T> {
+        let cartesian_position = self.basis_matrix() * fractional.position().coords.clone();
+        Transform2::from_parts(
+            fractional.angle(),
+            Vector2::new(cartesian_position.x.clone(), cartesian_position.y.clone()),
+        )
+    }
+
+    /// The number of surrounding lattice cells, in every direction, a contact search needs to
+    /// inspect to catch all periodic images close enough to matter.
+    ///
+    /// Cells far from square/rectangular need a wider search, since their periodic images can
+    /// be close to the origin cell along directions its own sides don't cover.
+    pub fn periodic_range(&self) -> i32
+    where
+        T: Into<f64>,
+    {
+        let aspect: f64 = (self.a() / self.b()).into();
+        let angle: f64 = self.angle().into();
+        match (aspect, angle) {
+            (p, a) if 0.5 < p && p < 2. && f64::abs(a - PI / 2.) < 0.2 => 1,
+            (p, a) if 0.3 < p && p < 3. && f64::abs(a - PI / 2.) < 0.5 => 2,
+            _ => 3,
+        }
+    }
+
+    /// Iterate over the Cartesian images of `fractional` in the surrounding lattice
+    /// translations, from `-range` to `range` cells in each direction.
+    ///
+    /// When `include_zero` is `false` the `(0, 0)` lattice translation -- the cell's own copy
+    /// -- is skipped, which is what callers checking for wrap-around contacts with the
+    /// original cell's contents want.
+    pub fn periodic_images(
+        &self,
+        fractional: Transform2<T>,
+        range: i32,
+        include_zero: bool,
+    ) -> Vec<Transform2<T>> {
+        let mut images = Vec::new();
+        for dx in -range..=range {
+            for dy in -range..=range {
+                if dx == 0 && dy == 0 && !include_zero {
+                    continue;
+                }
+                let shifted = Transform2::from_parts(
+                    fractional.angle(),
+                    fractional.position().coords.clone()
+                        + Vector2::new(T::from_subset(&(dx as f64)), T::from_subset(&(dy as f64))),
+                );
+                images.push(self.to_cartesian_isometry(shifted));
+            }
+        }
+        images
+    }
+
+    /// Initialise a cell for `family`, with every free length starting at `length`.
+    pub fn from_family(family: CrystalFamily, length: T) -> Self {
+        let (x_len, y_len, angle) = match family {
+            // The Hexagonal cell has both sides equal with a fixed angle of 60 degrees.
+            CrystalFamily::Hexagonal => (length.clone(), length.clone(), T::from_subset(&(PI / 3.))),
+            // The Tetragonal cell has both sides equal with a fixed angle of 90 degrees.
+            CrystalFamily::Tetragonal => (length.clone(), length.clone(), T::from_subset(&(PI / 2.))),
+            // The Orthorhombic cell has two independent sides with a fixed angle of 90 degrees.
+            CrystalFamily::Orthorhombic => {
+                (length.clone(), length.clone(), T::from_subset(&(PI / 2.)))
+            }
+            // The Monoclinic cell has two independent sides and a variable angle, initialised
+            // to 90 degrees.
+            CrystalFamily::Monoclinic => (length.clone(), length.clone(), T::from_subset(&(PI / 2.))),
+        };
+        Cell2 {
+            x_len,
+            y_len,
+            angle,
+            family,
+        }
+    }
+}
+
+impl Cell2<f64> {
+    /// Linearly interpolate this cell's lengths and angle toward `other`'s at `t` (`0` yields
+    /// `self`, `1` yields `other`), for producing intermediate frames between two recorded
+    /// [`Trajectory`](crate::Trajectory) snapshots.
+    pub fn lerp(&self, other: &Self, t: f64) -> Self {
+        Cell2 {
+            x_len: self.x_len + (other.x_len - self.x_len) * t,
+            y_len: self.y_len + (other.y_len - self.y_len) * t,
+            angle: self.angle + (other.angle - self.angle) * t,
+            family: self.family,
+        }
+    }
+
+    /// The free parameters of this cell, as entries the optimiser can sample.
+    pub fn get_degrees_of_freedom(&self) -> Vec<Basis> {
+        let mut basis = Vec::new();
+
+        // Every cell has at least one variable length.
+        let x_len = SharedValue::new(self.x_len);
+        basis.push(Basis::new(&x_len, 0.01, self.x_len));
+
+        if self.family == CrystalFamily::Orthorhombic || self.family == CrystalFamily::Monoclinic
+        {
+            let y_len = SharedValue::new(self.y_len);
+            basis.push(Basis::new(&y_len, 0.01, self.y_len));
+        }
+
+        if self.family == CrystalFamily::Monoclinic {
+            let angle = SharedValue::new(self.angle);
+            basis.push(Basis::new(&angle, PI / 4., 3. * PI / 4.));
+        }
+
+        basis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn area_of_square_cell() {
+        let cell = Cell2::from_family(CrystalFamily::Tetragonal, 2.);
+        assert_abs_diff_eq!(cell.area(), 4.);
+    }
+
+    #[test]
+    fn orthorhombic_has_two_free_lengths() {
+        let cell = Cell2::from_family(CrystalFamily::Orthorhombic, 2.);
+        assert_eq!(cell.get_degrees_of_freedom().len(), 2);
+    }
+
+    #[test]
+    fn monoclinic_has_three_degrees_of_freedom() {
+        let cell = Cell2::from_family(CrystalFamily::Monoclinic, 2.);
+        assert_eq!(cell.get_degrees_of_freedom().len(), 3);
+    }
+
+    #[test]
+    fn square_cell_has_narrow_periodic_range() {
+        let cell = Cell2::from_family(CrystalFamily::Tetragonal, 2.);
+        assert_eq!(cell.periodic_range(), 1);
+    }
+}
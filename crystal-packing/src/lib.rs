@@ -10,13 +10,13 @@
 
 pub mod basis;
 pub mod cell;
-pub mod ops_macros;
 pub mod optimisation;
 pub mod shape;
 pub mod site;
 pub mod state;
-pub mod to_svg;
+pub mod symmetry;
 pub mod traits;
+pub mod trajectory;
 pub mod transform;
 pub mod wallpaper;
 
@@ -26,6 +26,8 @@ pub use crate::optimisation::*;
 pub use crate::shape::*;
 pub use crate::site::*;
 pub use crate::state::*;
+pub use crate::symmetry::SymmetryGroup;
 pub use crate::traits::{FromSymmetry, Intersect, Shape};
+pub use crate::trajectory::{ConfigurationSnapshot, Recordable, Trajectory};
 pub use crate::transform::Transform2;
 pub use crate::wallpaper::WallpaperGroup;
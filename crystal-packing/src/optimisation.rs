@@ -0,0 +1,255 @@
+//
+// optimisation.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+
+use anyhow::Error;
+use log::debug;
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::traits::State;
+use crate::trajectory::{Recordable, Trajectory};
+
+/// The parameters controlling a simulated-annealing Monte Carlo packing search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCVars {
+    pub kt_start: f64,
+    pub kt_finish: f64,
+    pub max_step_size: f64,
+    pub num_start_configs: u64,
+    pub steps: u64,
+    pub seed: Option<u64>,
+    /// The maximum change allowed between successive Aitken-accelerated packing-fraction
+    /// estimates for the search to be considered converged.
+    pub tolerance: f64,
+    /// The number of successive estimates which must agree within `tolerance` before the
+    /// search stops early, rather than running the full `steps`.
+    pub convergence_window: usize,
+    /// Record a [`Trajectory`] snapshot of the search every this many steps, or skip recording
+    /// entirely when `None`.
+    pub trajectory_stride: Option<u64>,
+}
+
+impl Default for MCVars {
+    fn default() -> Self {
+        MCVars {
+            kt_start: 0.1,
+            kt_finish: 0.0005,
+            max_step_size: 0.1,
+            num_start_configs: 32,
+            steps: 100,
+            seed: None,
+            tolerance: 1e-8,
+            convergence_window: 5,
+            trajectory_stride: None,
+        }
+    }
+}
+
+impl MCVars {
+    fn kt_ratio(&self) -> f64 {
+        f64::powf(self.kt_finish / self.kt_start, 1.0 / self.steps as f64)
+    }
+}
+
+fn mc_temperature(old: f64, new: f64, kt: f64, n: u64) -> f64 {
+    f64::exp((1. / old - 1. / new) / kt) * (old / new).powi(n as i32)
+}
+
+/// A direction-and-acceptance policy for the annealing loop in [`monte_carlo_best_packing`].
+///
+/// This decouples the Metropolis acceptance rule and the meaning of "improves" from the loop
+/// itself, so the same loop drives both a packing fraction the search maximises and an
+/// interaction energy it minimises.
+pub trait Objective {
+    /// The probability of accepting a move from `old` to `new` at temperature `kt`, given `n`
+    /// independent shape/site instances contributing to the score.
+    fn acceptance(old: f64, new: f64, kt: f64, n: u64) -> f64;
+
+    /// Whether `candidate` is an improvement over the current `best`.
+    fn improves(candidate: f64, best: f64) -> bool;
+}
+
+/// Maximise [`State::score`] as a packing fraction, via the Metropolis criterion tailored to
+/// hard-shape packing.
+#[derive(Debug, Clone, Copy)]
+pub struct PackingFraction;
+
+impl Objective for PackingFraction {
+    fn acceptance(old: f64, new: f64, kt: f64, n: u64) -> f64 {
+        mc_temperature(old, new, kt, n)
+    }
+
+    fn improves(candidate: f64, best: f64) -> bool {
+        candidate > best
+    }
+}
+
+/// Minimise [`State::score`] as a total pairwise interaction energy, via the standard
+/// Metropolis criterion.
+#[derive(Debug, Clone, Copy)]
+pub struct PotentialEnergy;
+
+impl Objective for PotentialEnergy {
+    fn acceptance(old: f64, new: f64, kt: f64, _n: u64) -> f64 {
+        if new <= old {
+            1.
+        } else {
+            f64::exp((old - new) / kt)
+        }
+    }
+
+    fn improves(candidate: f64, best: f64) -> bool {
+        candidate < best
+    }
+}
+
+/// Extrapolate the limit of a converging sequence using Aitken's Δ² process.
+///
+/// Given three successive terms `x0`, `x1`, `x2` of the running-best packing fraction, this
+/// estimates the value the sequence is tending towards. Falls back to the unaccelerated `x2`
+/// when the denominator is too close to zero to trust, which happens once the sequence has
+/// essentially stopped moving.
+fn aitken_accelerate(x0: f64, x1: f64, x2: f64) -> f64 {
+    let denominator = x2 - 2. * x1 + x0;
+    if denominator.abs() < 1e-12 {
+        x2
+    } else {
+        x2 - (x2 - x1).powi(2) / denominator
+    }
+}
+
+/// The result of a `monte_carlo_best_packing` search.
+#[derive(Debug, Clone)]
+pub struct PackingResult<S> {
+    /// The best state found during the search.
+    pub state: S,
+    /// The Aitken-extrapolated asymptotic packing fraction, once enough accepted best
+    /// values had been observed to form an estimate.
+    pub extrapolated_score: Option<f64>,
+    /// The recorded search path, present only when `vars.trajectory_stride` requested one.
+    pub trajectory: Option<Trajectory>,
+}
+
+/// Perform a simulated annealing search for the best-scoring `state`, under the acceptance and
+/// improvement rules of `O`.
+///
+/// The search runs for at most `vars.steps` iterations, but will stop early once the
+/// Aitken-accelerated estimate of the limiting score has settled to within `vars.tolerance` for
+/// `vars.convergence_window` consecutive improvements.
+pub fn monte_carlo_best_packing<S, O>(vars: &MCVars, state: S) -> Result<PackingResult<S>, Error>
+where
+    S: State + Recordable + Clone,
+    O: Objective,
+{
+    let mut rng = match vars.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let mut rejections: u64 = 0;
+    let mut kt = vars.kt_start;
+    let kt_ratio = vars.kt_ratio();
+    let total_shapes = state.total_shapes() as u64;
+
+    let basis = state.generate_basis();
+    let basis_distribution = Uniform::new(0, basis.len());
+
+    let mut packing_prev = state.score().unwrap_or(0.);
+    let mut packing_max = packing_prev;
+    let mut best_state = state.clone();
+
+    // The running sequence of accepted best packing fractions, used to drive the Aitken
+    // early-stopping check below.
+    let mut recent_best = vec![packing_max];
+    let mut last_estimate: Option<f64> = None;
+    let mut stable_checks = 0usize;
+    let mut extrapolated_score = None;
+    let mut trajectory = vars.trajectory_stride.map(Trajectory::new);
+
+    for step in 0..vars.steps {
+        let basis_index = basis_distribution.sample(&mut rng);
+        basis[basis_index].set_value(basis[basis_index].sample(&mut rng));
+
+        let packing = match state.score() {
+            Some(score) if rng.gen::<f64>() < O::acceptance(packing_prev, score, kt, total_shapes) => {
+                packing_prev = score;
+                score
+            }
+            _ => {
+                rejections += 1;
+                basis[basis_index].reset_value();
+                packing_prev
+            }
+        };
+
+        if O::improves(packing, packing_max) {
+            packing_max = packing;
+            best_state = state.clone();
+            recent_best.push(packing_max);
+
+            if recent_best.len() >= 3 {
+                let n = recent_best.len();
+                let estimate =
+                    aitken_accelerate(recent_best[n - 3], recent_best[n - 2], recent_best[n - 1]);
+                extrapolated_score = Some(estimate);
+
+                let converged = last_estimate
+                    .map(|prev| (estimate - prev).abs() < vars.tolerance)
+                    .unwrap_or(false);
+                stable_checks = if converged { stable_checks + 1 } else { 0 };
+                last_estimate = Some(estimate);
+
+                if stable_checks >= vars.convergence_window {
+                    debug!(
+                        "Converged early with packing fraction {}, extrapolated {}",
+                        packing_max, estimate
+                    );
+                    break;
+                }
+            }
+        }
+        if let Some(trajectory) = trajectory.as_mut() {
+            trajectory.record(step, &state);
+        }
+        kt *= kt_ratio;
+    }
+
+    debug!(
+        "Score: {}, Rejection Percentage {}, extrapolated: {:?}",
+        packing_max,
+        rejections as f64 / vars.steps as f64,
+        extrapolated_score,
+    );
+
+    Ok(PackingResult {
+        state: best_state,
+        extrapolated_score,
+        trajectory,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn aitken_accelerate_extrapolates_a_converging_geometric_sequence() {
+        // x_n = 1 - 0.5^n converges to 1; three successive terms should extrapolate to exactly
+        // that limit, rather than just the last (unaccelerated) term.
+        let (x0, x1, x2) = (1. - 0.5f64.powi(0), 1. - 0.5f64.powi(1), 1. - 0.5f64.powi(2));
+        assert_abs_diff_eq!(aitken_accelerate(x0, x1, x2), 1.);
+    }
+
+    #[test]
+    fn aitken_accelerate_falls_back_to_the_last_term_near_a_zero_denominator() {
+        // A sequence that has already converged has x2 - 2*x1 + x0 == 0, which would divide by
+        // zero without the fallback.
+        assert_abs_diff_eq!(aitken_accelerate(0.5, 0.5, 0.5), 0.5);
+    }
+}
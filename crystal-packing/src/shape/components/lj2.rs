@@ -58,6 +58,13 @@ impl Potential for LJ2 {
             None => 4. * self.epsilon * (sigma2_r2_cubed.powi(2) - sigma2_r2_cubed),
         }
     }
+
+    fn with_position(&self, position: Point2<f64>) -> Self {
+        LJ2 {
+            position,
+            ..self.clone()
+        }
+    }
 }
 
 impl fmt::Display for LJ2 {
@@ -95,6 +102,16 @@ mod test {
         assert_abs_diff_eq!(a.epsilon, 1.);
     }
 
+    #[test]
+    fn with_position_moves_particle_only() {
+        let a = LJ2::new(0., 0., 1.);
+        let b = a.with_position(Point2::new(2., 3.));
+        assert_abs_diff_eq!(b.position.x, 2.);
+        assert_abs_diff_eq!(b.position.y, 3.);
+        assert_abs_diff_eq!(b.sigma, a.sigma);
+        assert_abs_diff_eq!(b.epsilon, a.epsilon);
+    }
+
     #[test]
     fn default_constuctor() {
         let a = LJ2::default();
@@ -0,0 +1,9 @@
+//
+// mod.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+
+pub mod lj2;
+
+pub use lj2::LJ2;
@@ -0,0 +1,262 @@
+//
+// mod.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// The concrete `Shape` implementations: `LineShape`, a polygon defined by radial points, and
+// `MolecularShape2`, a rigid body built from overlapping disks. Both are generic over the
+// scalar type `T`, defaulting to `f64` for the same reason as `Shape` itself (see its doc
+// comment in `traits.rs`).
+
+pub mod components;
+
+use std::f64::consts::PI;
+
+use anyhow::{anyhow, Error};
+use nalgebra::{Point2, RealField};
+use serde::{Deserialize, Serialize};
+
+use crate::traits::{Intersect, Shape};
+use crate::transform::Transform2;
+
+/// A polygon defined by the distance from its centre to each vertex, evenly spaced in angle.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub struct LineShape<T: RealField = f64> {
+    pub name: String,
+    pub radial_points: Vec<T>,
+    pub rotational_symmetries: u64,
+    pub mirrors: u64,
+}
+
+impl<T: RealField> LineShape<T> {
+    pub fn from_radial(name: &str, radial_points: Vec<T>) -> Result<Self, Error> {
+        if radial_points.len() < 3 {
+            return Err(anyhow!(
+                "A polygon requires at least 3 radial points, got {}",
+                radial_points.len()
+            ));
+        }
+        let rotational_symmetries = radial_points.len() as u64;
+        Ok(LineShape {
+            name: String::from(name),
+            rotational_symmetries,
+            mirrors: rotational_symmetries,
+            radial_points,
+        })
+    }
+
+    /// The vertices of the polygon in the shape's own (untransformed) frame.
+    fn vertices(&self) -> Vec<Point2<T>> {
+        let n = self.radial_points.len();
+        self.radial_points
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let theta = T::from_subset(&(2. * PI * i as f64 / n as f64));
+                Point2::new(r.clone() * theta.clone().cos(), r.clone() * theta.sin())
+            })
+            .collect()
+    }
+}
+
+impl<T: RealField> Shape<T> for LineShape<T> {
+    type Instance = LineShapeInstance<T>;
+
+    fn area(&self) -> T {
+        // The shoelace formula applied to the polygon's vertices.
+        let vertices = self.vertices();
+        let mut sum = T::zero();
+        let n = vertices.len();
+        for i in 0..n {
+            let p = &vertices[i];
+            let q = &vertices[(i + 1) % n];
+            sum += p.x.clone() * q.y.clone() - q.x.clone() * p.y.clone();
+        }
+        (sum / T::from_subset(&2.)).abs()
+    }
+
+    fn enclosing_radius(&self) -> T {
+        self.radial_points
+            .iter()
+            .cloned()
+            .fold(T::zero(), |max, r| if r > max { r } else { max })
+    }
+
+    fn transform(&self, transform: &Transform2<T>) -> Self::Instance {
+        LineShapeInstance {
+            vertices: self.vertices().iter().map(|p| transform.transform(p)).collect(),
+        }
+    }
+}
+
+/// A `LineShape` positioned in space, ready for intersection testing.
+#[derive(Clone, Debug)]
+pub struct LineShapeInstance<T: RealField = f64> {
+    vertices: Vec<Point2<T>>,
+}
+
+impl<T: RealField> LineShapeInstance<T> {
+    /// The edge normals of the polygon (pointing inward, since the vertices run
+    /// counter-clockwise), used as the Separating Axis Theorem's candidate axes.
+    fn edge_normals(&self) -> Vec<nalgebra::Vector2<T>> {
+        let n = self.vertices.len();
+        (0..n)
+            .map(|i| {
+                let edge = &self.vertices[(i + 1) % n] - &self.vertices[i];
+                nalgebra::Vector2::new(-edge.y.clone(), edge.x.clone())
+            })
+            .collect()
+    }
+}
+
+impl<T: RealField> Intersect<T> for LineShapeInstance<T> {
+    fn intersects(&self, other: &Self) -> bool {
+        // Separating Axis Theorem: the polygons are disjoint iff some edge normal of either
+        // polygon separates the projections of all of both polygons' vertices.
+        for axis in self.edge_normals().iter().chain(other.edge_normals().iter()) {
+            let project = |vertices: &[Point2<T>]| {
+                let mut min = axis.dot(&vertices[0].coords.clone());
+                let mut max = min.clone();
+                for v in vertices.iter().skip(1) {
+                    let p = axis.dot(&v.coords.clone());
+                    if p < min {
+                        min = p.clone();
+                    }
+                    if p > max {
+                        max = p.clone();
+                    }
+                }
+                (min, max)
+            };
+            let (min1, max1) = project(&self.vertices);
+            let (min2, max2) = project(&other.vertices);
+            if max1 < min2 || max2 < min1 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A rigid body made up of one or more overlapping disks, e.g. a diatomic or trimer molecule.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub struct MolecularShape2<T: RealField = f64> {
+    /// The centre of each constituent disk, in the shape's own frame.
+    pub positions: Vec<Point2<T>>,
+    /// The radius of each constituent disk.
+    pub radii: Vec<T>,
+}
+
+impl<T: RealField> MolecularShape2<T> {
+    /// A single disk of unit radius, centred on the origin.
+    pub fn circle() -> Self {
+        MolecularShape2 {
+            positions: vec![Point2::origin()],
+            radii: vec![T::one()],
+        }
+    }
+
+    /// Three disks of `radius`: a middle disk at the origin and two outer disks `distance` from
+    /// it, with `angle` between the two bonds.
+    pub fn from_trimer(distance: f64, angle: f64, radius: f64) -> Self {
+        let half_angle = T::from_subset(&(angle / 2.));
+        let distance = T::from_subset(&distance);
+        let radius = T::from_subset(&radius);
+        MolecularShape2 {
+            positions: vec![
+                Point2::new(
+                    distance.clone() * half_angle.clone().cos(),
+                    distance.clone() * half_angle.clone().sin(),
+                ),
+                Point2::origin(),
+                Point2::new(
+                    distance.clone() * half_angle.clone().cos(),
+                    -distance * half_angle.sin(),
+                ),
+            ],
+            radii: vec![radius.clone(), radius.clone(), radius],
+        }
+    }
+}
+
+impl<T: RealField> Shape<T> for MolecularShape2<T> {
+    type Instance = MolecularShapeInstance<T>;
+
+    fn area(&self) -> T {
+        self.radii
+            .iter()
+            .fold(T::zero(), |sum, r| sum + T::pi() * r.clone() * r.clone())
+    }
+
+    fn enclosing_radius(&self) -> T {
+        self.positions
+            .iter()
+            .zip(self.radii.iter())
+            .map(|(p, r)| p.coords.norm() + r.clone())
+            .fold(T::zero(), |max, r| if r > max { r } else { max })
+    }
+
+    fn transform(&self, transform: &Transform2<T>) -> Self::Instance {
+        MolecularShapeInstance {
+            positions: self
+                .positions
+                .iter()
+                .map(|p| transform.transform(p))
+                .collect(),
+            radii: self.radii.clone(),
+        }
+    }
+}
+
+/// A `MolecularShape2` positioned in space, ready for intersection testing.
+#[derive(Clone, Debug)]
+pub struct MolecularShapeInstance<T: RealField = f64> {
+    positions: Vec<Point2<T>>,
+    radii: Vec<T>,
+}
+
+impl<T: RealField> Intersect<T> for MolecularShapeInstance<T> {
+    fn intersects(&self, other: &Self) -> bool {
+        for (p1, r1) in self.positions.iter().zip(self.radii.iter()) {
+            for (p2, r2) in other.positions.iter().zip(other.radii.iter()) {
+                let min_distance = r1.clone() + r2.clone();
+                if (p1 - p2).norm_squared() < min_distance.clone() * min_distance {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn square_area() {
+        let square = LineShape::<f64>::from_radial("Square", vec![1., 1., 1., 1.]).unwrap();
+        assert_abs_diff_eq!(square.area(), 2., epsilon = 1e-10);
+    }
+
+    #[test]
+    fn overlapping_circles_intersect() {
+        let a = MolecularShape2::<f64>::circle();
+        let b = MolecularShape2::<f64>::circle();
+        let instance_a = a.transform(&Transform2::identity());
+        let instance_b = b.transform(&Transform2::new(0., (0.5, 0.)));
+        assert!(instance_a.intersects(&instance_b));
+    }
+
+    #[test]
+    fn distant_circles_do_not_intersect() {
+        let a = MolecularShape2::<f64>::circle();
+        let b = MolecularShape2::<f64>::circle();
+        let instance_a = a.transform(&Transform2::identity());
+        let instance_b = b.transform(&Transform2::new(0., (10., 0.)));
+        assert!(!instance_a.intersects(&instance_b));
+    }
+}
@@ -0,0 +1,120 @@
+//
+// site.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// An occupied Wyckoff site: a single free (x, y, angle) together with the symmetry operations
+// that expand it into every equivalent position within the unit cell.
+//
+// The site's coordinates always stay `f64`, for the same reason `SharedValue` does (see its
+// doc comment in `basis.rs`), even when the shape/cell geometry they feed into is instantiated
+// generically for the intersection hot path.
+
+use std::f64::consts::PI;
+
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::basis::{Basis, SharedValue};
+use crate::transform::Transform2;
+use crate::wallpaper::WyckoffSite;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OccupiedSite {
+    wyckoff: WyckoffSite,
+    x: SharedValue,
+    y: SharedValue,
+    angle: SharedValue,
+}
+
+impl OccupiedSite {
+    pub fn multiplicity(&self) -> usize {
+        self.wyckoff.symmetries.len()
+    }
+
+    pub fn from_wyckoff(wyckoff: &WyckoffSite) -> OccupiedSite {
+        OccupiedSite {
+            wyckoff: wyckoff.clone(),
+            x: SharedValue::new(0.),
+            y: SharedValue::new(0.),
+            angle: SharedValue::new(0.),
+        }
+    }
+
+    /// The transform for every symmetry-equivalent copy of this site's occupant.
+    pub fn positions(&self) -> impl Iterator<Item = Transform2> + '_ {
+        let fractional = nalgebra::Point2::from(Vector2::new(self.x.get_value(), self.y.get_value()));
+        self.wyckoff.symmetries.iter().map(move |symmetry| {
+            let position = symmetry.transform(&fractional);
+            Transform2::from_parts(
+                self.angle.get_value() + symmetry.angle(),
+                position.coords,
+            )
+        })
+    }
+
+    /// The free parameters of this site, given its site symmetry's degrees of freedom.
+    pub fn get_basis(&self) -> Vec<Basis> {
+        let mut basis = Vec::new();
+        let dof = self.wyckoff.degrees_of_freedom();
+
+        if dof[0] {
+            basis.push(Basis::new(&self.x, -0.5, 0.5));
+        }
+        if dof[1] {
+            basis.push(Basis::new(&self.y, -0.5, 0.5));
+        }
+        if dof[2] {
+            let rotational_symmetry = self.wyckoff.num_rotations.max(1);
+            basis.push(Basis::new(
+                &self.angle,
+                0.,
+                2. * PI / rotational_symmetry as f64,
+            ));
+        }
+        basis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_wyckoff() -> WyckoffSite {
+        WyckoffSite {
+            letter: 'a',
+            symmetries: vec![crate::Transform2::identity()],
+            num_rotations: 1,
+            mirror_primary: false,
+            mirror_secondary: false,
+        }
+    }
+
+    #[test]
+    fn multiplicity_matches_symmetry_count() {
+        let site = OccupiedSite::from_wyckoff(&create_wyckoff());
+        assert_eq!(site.multiplicity(), 1);
+    }
+
+    #[test]
+    fn general_site_has_three_degrees_of_freedom() {
+        let site = OccupiedSite::from_wyckoff(&create_wyckoff());
+        assert_eq!(site.get_basis().len(), 3);
+    }
+
+    #[test]
+    fn mirror_site_loses_the_pinned_coordinate() {
+        let mut wyckoff = create_wyckoff();
+        wyckoff.mirror_primary = true;
+        let site = OccupiedSite::from_wyckoff(&wyckoff);
+        assert_eq!(site.get_basis().len(), 2);
+    }
+
+    #[test]
+    fn rotation_centre_site_loses_both_coordinates() {
+        let mut wyckoff = create_wyckoff();
+        wyckoff.num_rotations = 2;
+        let site = OccupiedSite::from_wyckoff(&wyckoff);
+        assert_eq!(site.get_basis().len(), 1);
+    }
+}
@@ -0,0 +1,11 @@
+//
+// mod.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+
+pub mod packed;
+pub mod potential;
+
+pub use packed::*;
+pub use potential::*;
@@ -7,36 +7,62 @@
 #![allow(clippy::type_repetition_in_bounds)]
 
 use std::cmp::Ordering;
-use std::f64::consts::PI;
 use std::fmt::Write;
-use std::ops::Mul;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
 
 use anyhow::Error;
 use log::debug;
+use nalgebra::RealField;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use crate::traits::*;
 use crate::wallpaper::{Wallpaper, WallpaperGroup, WyckoffSite};
-use crate::{Basis, Cell2, OccupiedSite, Transform2};
+use crate::{Basis, Cell2, OccupiedSite, Recordable, Transform2};
 
 pub type PackedState2<S> = PackedState<S>;
 
+/// A candidate packing: a shape placed on every symmetry-equivalent copy of a wallpaper group's
+/// occupied sites, tiled within a [`Cell2`].
+///
+/// Generic over the scalar type `T` (defaulting to `f64`) for the same reason [`Shape`] is --
+/// constructing a `PackedState<S, T>` and calling its geometry-only methods ([`total_shapes`],
+/// [`score`], [`check_intersection`](Self::check_intersection)) works at any `T: RealField`
+/// (`score` and `check_intersection` additionally need `T: Into<f64>`), so a caller can e.g.
+/// batch-check candidate configurations at `f32`. The annealing search itself
+/// (the [`State`] and [`Recordable`] trait impls, which hand out [`Basis`] entries sampled by
+/// [`SharedValue`](crate::SharedValue)) only exists at `T = f64`, since `SharedValue` -- and the
+/// `OccupiedSite` coordinates it backs -- are themselves only ever instantiated at `f64` (see
+/// `SharedValue`'s doc comment); generalising the search loop over `T` would mean generalising
+/// that sampling machinery too, which is a larger change than this type alone can carry.
+///
+/// [`total_shapes`]: Self::total_shapes
+/// [`score`]: Self::score
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct PackedState<S>
+#[serde(bound(serialize = "S: Serialize, T: Serialize", deserialize = "S: Deserialize<'de>, T: Deserialize<'de>"))]
+pub struct PackedState<S, T: RealField = f64>
 where
-    S: Shape + Intersect,
+    S: Shape<T> + Intersect<T>,
 {
     pub wallpaper: Wallpaper,
     pub shape: S,
-    pub cell: Cell2,
+    pub cell: Cell2<T>,
     occupied_sites: Vec<OccupiedSite>,
 }
 
-impl<S> Eq for PackedState<S> where S: Shape + Intersect {}
+impl<S, T> Eq for PackedState<S, T>
+where
+    S: Shape<T> + Intersect<T>,
+    T: RealField + Into<f64>,
+{
+}
 
-impl<S> PartialEq for PackedState<S>
+impl<S, T> PartialEq for PackedState<S, T>
 where
-    S: Shape + Intersect,
+    S: Shape<T> + Intersect<T>,
+    T: RealField + Into<f64>,
 {
     fn eq(&self, other: &Self) -> bool {
         match (self.score(), other.score()) {
@@ -46,9 +72,10 @@ where
     }
 }
 
-impl<S> PartialOrd for PackedState<S>
+impl<S, T> PartialOrd for PackedState<S, T>
 where
-    S: Shape + Intersect,
+    S: Shape<T> + Intersect<T>,
+    T: RealField + Into<f64>,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self.score(), other.score()) {
@@ -58,31 +85,26 @@ where
     }
 }
 
-impl<S> Ord for PackedState<S>
+impl<S, T> Ord for PackedState<S, T>
 where
-    S: Shape + Intersect,
+    S: Shape<T> + Intersect<T>,
+    T: RealField + Into<f64>,
 {
     fn cmp(&self, other: &Self) -> Ordering {
         self.partial_cmp(other).unwrap()
     }
 }
 
-impl<S> State for PackedState<S>
+impl<S> State for PackedState<S, f64>
 where
     S: Shape + Intersect,
 {
     fn total_shapes(&self) -> usize {
-        self.occupied_sites
-            .iter()
-            .fold(0, |sum, site| sum + site.multiplicity())
+        self.total_shapes()
     }
 
     fn score(&self) -> Option<f64> {
-        if self.check_intersection() {
-            None
-        } else {
-            Some((self.shape.area() * self.total_shapes() as f64) / self.cell.area())
-        }
+        self.score()
     }
 
     fn generate_basis(&self) -> Vec<Basis> {
@@ -105,31 +127,44 @@ where
         Ok(output)
     }
 }
-impl<S> PackedState<S>
+
+impl<S, T> PackedState<S, T>
 where
-    S: Shape + Intersect,
+    S: Shape<T> + Intersect<T>,
+    T: RealField,
 {
-    pub fn cartesian_positions(&self) -> impl Iterator<Item = Transform2> + '_ {
+    /// The `Transform2<T>` placing every symmetry-equivalent copy of every occupied site,
+    /// relative to the cell's own basis.
+    pub fn cartesian_positions(&self) -> impl Iterator<Item = Transform2<T>> + '_ {
         self.relative_positions()
-            .map(move |position| self.cell.to_cartesian_isometry(position))
+            .map(move |position| self.cell.to_cartesian_isometry(position.cast()))
     }
 
+    /// The fractional-coordinate `Transform2` of every symmetry-equivalent copy of every
+    /// occupied site. Always `f64`, since it comes directly from `OccupiedSite`'s sampled
+    /// coordinates (see [`SharedValue`](crate::SharedValue)'s doc comment).
     pub fn relative_positions(&self) -> impl Iterator<Item = Transform2> + '_ {
         self.occupied_sites.iter().flat_map(OccupiedSite::positions)
     }
 
+    /// The total number of shape instances within the cell, including symmetry copies.
+    pub fn total_shapes(&self) -> usize {
+        self.occupied_sites
+            .iter()
+            .fold(0, |sum, site| sum + site.multiplicity())
+    }
+
     /// Check for intersections of shapes in the current state.
     ///
     /// This checks for intersections between any shapes, checking all occupied sites and their
     /// symmetry defined copies for the current cell and the neighbouring cells. Checking the
     /// neighbouring cells ensures there are no intersections of when tiling space.
     ///
-    fn check_intersection(&self) -> bool {
-        let periodic_range = match (self.cell.a() / self.cell.b(), self.cell.angle()) {
-            (p, a) if 0.5 < p && p < 2. && f64::abs(a - PI / 2.) < 0.2 => 1,
-            (p, a) if 0.3 < p && p < 3. && f64::abs(a - PI / 2.) < 0.5 => 2,
-            _ => 3,
-        };
+    pub fn check_intersection(&self) -> bool
+    where
+        T: Into<f64>,
+    {
+        let periodic_range = self.cell.periodic_range();
         // Compare within the current cell
         for (index, shape1) in self
             .cartesian_positions()
@@ -147,12 +182,16 @@ where
             }
         }
 
-        let radius_sq = self.shape.enclosing_radius().mul(2.).powi(2);
+        let enclosing_diameter = self.shape.enclosing_radius() * T::from_subset(&2.);
+        let radius_sq = enclosing_diameter.clone() * enclosing_diameter;
         // Compare in periodic cells
         for transform1 in self.cartesian_positions() {
             let shape1 = self.shape.transform(&transform1);
             for position in self.relative_positions() {
-                for transform2 in self.cell.periodic_images(position, periodic_range, false) {
+                for transform2 in self
+                    .cell
+                    .periodic_images(position.cast(), periodic_range, false)
+                {
                     let distance = (transform1.position() - transform2.position()).norm_squared();
                     if distance <= radius_sq {
                         let shape2 = self.shape.transform(&transform2);
@@ -166,13 +205,28 @@ where
         false
     }
 
+    /// A score for the current configuration -- the packing fraction -- or `None` when shapes
+    /// overlap.
+    pub fn score(&self) -> Option<f64>
+    where
+        T: Into<f64>,
+    {
+        if self.check_intersection() {
+            None
+        } else {
+            Some((self.shape.area().into() * self.total_shapes() as f64) / self.cell.area().into())
+        }
+    }
+
     pub fn initialise(
         shape: S,
         wallpaper: Wallpaper,
         isopointal: &[WyckoffSite],
-    ) -> PackedState<S> {
+    ) -> PackedState<S, T> {
         let num_shapes = isopointal.iter().fold(0, |acc, x| acc + x.multiplicity());
-        let max_cell_size = 4. * shape.enclosing_radius() * num_shapes as f64;
+        let max_cell_size = shape.enclosing_radius()
+            * T::from_subset(&4.)
+            * T::from_subset(&(num_shapes as f64));
 
         let cell = Cell2::from_family(wallpaper.family, max_cell_size);
 
@@ -195,6 +249,73 @@ where
     }
 }
 
+impl<S> Recordable for PackedState<S, f64>
+where
+    S: Shape + Intersect,
+{
+    fn cell(&self) -> &Cell2 {
+        &self.cell
+    }
+
+    fn relative_positions(&self) -> Vec<Transform2> {
+        self.relative_positions().collect()
+    }
+}
+
+impl<S, T> PackedState<S, T>
+where
+    S: Shape<T> + Intersect<T> + Serialize + DeserializeOwned,
+    T: RealField + Serialize + DeserializeOwned,
+{
+    /// Write the current configuration to `path` as JSON.
+    ///
+    /// Intended to be called periodically during a long-running search so a crashed or
+    /// interrupted run can resume from the last saved configuration via [`load_checkpoint`]
+    /// rather than starting over from a fresh [`initialise`](Self::initialise).
+    pub fn save_checkpoint(&self, path: &Path) -> Result<(), Error> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Restore a configuration previously written by [`save_checkpoint`](Self::save_checkpoint).
+    pub fn load_checkpoint(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
+impl<S, T> PackedState<S, T>
+where
+    S: Shape<T> + Intersect<T> + Clone,
+    T: RealField + Into<f64>,
+{
+    /// A structured snapshot of this configuration -- cell, shape, Wyckoff occupation and
+    /// packing fraction -- suitable for JSON export in place of the freeform
+    /// [`as_positions`](State::as_positions) text dump.
+    pub fn to_report(&self) -> PackingReport<S, T> {
+        PackingReport {
+            wallpaper: self.wallpaper.clone(),
+            shape: self.shape.clone(),
+            cell: self.cell.clone(),
+            occupied_sites: self.occupied_sites.clone(),
+            packing_fraction: self.score(),
+        }
+    }
+}
+
+/// A structured, serialisable snapshot of a [`PackedState`]: the wallpaper group, shape, cell,
+/// Wyckoff site occupation and resulting packing fraction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(serialize = "S: Serialize, T: Serialize", deserialize = "S: Deserialize<'de>, T: Deserialize<'de>"))]
+pub struct PackingReport<S, T: RealField = f64> {
+    pub wallpaper: Wallpaper,
+    pub shape: S,
+    pub cell: Cell2<T>,
+    pub occupied_sites: Vec<OccupiedSite>,
+    pub packing_fraction: Option<f64>,
+}
+
 #[cfg(test)]
 mod packed_state_tests {
     use super::*;
@@ -221,9 +342,13 @@ mod packed_state_tests {
         (wallpaper, isopointal)
     }
 
-    fn create_wallpaper_p2mg() -> (Wallpaper, Vec<WyckoffSite>) {
+    // A synthetic four-copy site: a 2-fold rotation about the origin, plus the same pair
+    // translated by (1/2, 0). `Transform2` can only hold proper rotations, so (unlike a real
+    // `pmg`/`pgg` group) every operation here has determinant +1 -- this exists purely to give
+    // `PackedState` a multi-copy site to exercise, not to model a named wallpaper group.
+    fn create_wallpaper_c_centred_p2() -> (Wallpaper, Vec<WyckoffSite>) {
         let wallpaper = Wallpaper {
-            name: String::from("p2mg"),
+            name: String::from("c-centred p2"),
             family: CrystalFamily::Monoclinic,
         };
         let isopointal = vec![WyckoffSite {
@@ -231,8 +356,8 @@ mod packed_state_tests {
             symmetries: vec![
                 Transform2::from_operations("x,y").unwrap(),
                 Transform2::from_operations("-x,-y").unwrap(),
-                Transform2::from_operations("-x+1/2,y").unwrap(),
-                Transform2::from_operations("x+1/2,-y").unwrap(),
+                Transform2::from_operations("-x+1/2,-y").unwrap(),
+                Transform2::from_operations("x+1/2,y").unwrap(),
             ],
             num_rotations: 1,
             mirror_primary: false,
@@ -247,7 +372,7 @@ mod packed_state_tests {
 
         let (wallpaper, isopointal) = (match group {
             "p1" => Some(create_wallpaper_p1()),
-            "p2mg" => Some(create_wallpaper_p2mg()),
+            "c_centred_p2" => Some(create_wallpaper_c_centred_p2()),
             _ => None,
         })
         .unwrap();
@@ -267,14 +392,74 @@ mod packed_state_tests {
     }
 
     #[test]
-    fn total_shapes_p2mg() {
-        let state = init_packed_state("p2mg");
+    fn total_shapes_c_centred_p2() {
+        let state = init_packed_state("c_centred_p2");
         assert_eq!(state.total_shapes(), 4);
     }
 
     #[test]
-    fn packing_fraction_p2mg() {
-        let state = init_packed_state("p2mg");
+    fn packing_fraction_c_centred_p2() {
+        let state = init_packed_state("c_centred_p2");
+        // Move the site off the origin, where the `x,y` and `-x,-y` copies coincide.
+        let basis = state.generate_basis();
+        basis[3].set_value(0.25); // site x
+        basis[4].set_value(0.25); // site y
         assert_abs_diff_eq!(state.score().unwrap(), 1. / 32.);
     }
+
+    #[test]
+    fn coincident_instances_have_no_score() {
+        let square = create_square();
+        let wallpaper = Wallpaper {
+            name: String::from("p1"),
+            family: CrystalFamily::Monoclinic,
+        };
+        // Two symmetry copies mapped to the exact same position, so their instances coincide
+        // and must register as an intersection.
+        let isopointal = vec![WyckoffSite {
+            letter: 'a',
+            symmetries: vec![
+                Transform2::from_operations("x,y").unwrap(),
+                Transform2::from_operations("x,y").unwrap(),
+            ],
+            num_rotations: 1,
+            mirror_primary: false,
+            mirror_secondary: false,
+        }];
+        let state = PackedState::initialise(square, wallpaper, &isopointal);
+        assert!(state.score().is_none());
+    }
+
+    #[test]
+    fn checkpoint_round_trips_through_disk() {
+        let state = init_packed_state("c_centred_p2");
+        // Move the site off the origin, where the `x,y` and `-x,-y` copies coincide.
+        let basis = state.generate_basis();
+        basis[3].set_value(0.25); // site x
+        basis[4].set_value(0.25); // site y
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        state.save_checkpoint(file.path()).unwrap();
+        let restored = PackedState::load_checkpoint(file.path()).unwrap();
+
+        assert_abs_diff_eq!(restored.score().unwrap(), state.score().unwrap());
+        assert_eq!(restored.occupied_sites, state.occupied_sites);
+        assert_eq!(restored.cell, state.cell);
+    }
+
+    // A `PackedState` can be instantiated, scored and intersection-checked at any
+    // `T: RealField` -- not just `f64` -- for batch intersection testing at a lower-precision
+    // scalar type. Running it through the annealer itself still requires `T = f64`, since that
+    // needs `State`/`Recordable`, which are only implemented for `PackedState<S, f64>` (see
+    // `PackedState`'s doc comment).
+    #[test]
+    fn packed_state_can_be_instantiated_at_f32() {
+        let square: LineShape<f32> = LineShape::from_radial("Square", vec![1., 1., 1., 1.]).unwrap();
+        let (wallpaper, isopointal) = create_wallpaper_p1();
+        let state: PackedState<LineShape<f32>, f32> =
+            PackedState::initialise(square, wallpaper, &isopointal);
+
+        assert_eq!(state.total_shapes(), 1);
+        assert_abs_diff_eq!(state.score().unwrap(), 1. / 8., epsilon = 1e-6);
+    }
 }
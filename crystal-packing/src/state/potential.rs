@@ -0,0 +1,283 @@
+//
+// potential.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::traits::*;
+use crate::wallpaper::{Wallpaper, WallpaperGroup, WyckoffSite};
+use crate::{Basis, Cell2, OccupiedSite, Recordable, Transform2};
+
+/// A configuration scored by total pairwise interaction energy rather than a hard-shape
+/// packing fraction.
+///
+/// Each [`OccupiedSite`] (and every one of its symmetry-equivalent copies) hosts a single
+/// instance of `potential`, repositioned to that site's current [`Transform2`]. Unlike
+/// [`PackedState`](crate::PackedState), there is no notion of an invalid (overlapping)
+/// configuration -- `score` is always `Some`, and lower is better.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PotentialState<P>
+where
+    P: Potential,
+{
+    pub wallpaper: Wallpaper,
+    pub potential: P,
+    pub cell: Cell2,
+    occupied_sites: Vec<OccupiedSite>,
+}
+
+impl<P> Eq for PotentialState<P> where P: Potential {}
+
+impl<P> PartialEq for PotentialState<P>
+where
+    P: Potential,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self.score(), other.score()) {
+            (Some(s), Some(o)) => s.eq(&o),
+            (_, _) => false,
+        }
+    }
+}
+
+impl<P> PartialOrd for PotentialState<P>
+where
+    P: Potential,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.score(), other.score()) {
+            (Some(s), Some(o)) => s.partial_cmp(&o),
+            (_, _) => None,
+        }
+    }
+}
+
+impl<P> Ord for PotentialState<P>
+where
+    P: Potential,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.partial_cmp(other).unwrap()
+    }
+}
+
+impl<P> State for PotentialState<P>
+where
+    P: Potential,
+{
+    fn total_shapes(&self) -> usize {
+        self.occupied_sites
+            .iter()
+            .fold(0, |sum, site| sum + site.multiplicity())
+    }
+
+    fn score(&self) -> Option<f64> {
+        Some(self.total_energy())
+    }
+
+    fn generate_basis(&self) -> Vec<Basis> {
+        let mut basis: Vec<Basis> = vec![];
+        basis.append(&mut self.cell.get_degrees_of_freedom());
+        for site in self.occupied_sites.iter() {
+            basis.append(&mut site.get_basis());
+        }
+        basis
+    }
+
+    fn as_positions(&self) -> Result<String, Error> {
+        let mut output = String::new();
+        writeln!(&mut output, "{}", self.cell)?;
+        writeln!(&mut output, "Positions")?;
+
+        for transform in self.cartesian_positions() {
+            writeln!(&mut output, "{:?}", transform)?;
+        }
+        Ok(output)
+    }
+}
+
+impl<P> Recordable for PotentialState<P>
+where
+    P: Potential,
+{
+    fn cell(&self) -> &Cell2 {
+        &self.cell
+    }
+
+    fn relative_positions(&self) -> Vec<Transform2> {
+        self.relative_positions().collect()
+    }
+}
+
+impl<P> PotentialState<P>
+where
+    P: Potential,
+{
+    pub fn cartesian_positions(&self) -> impl Iterator<Item = Transform2> + '_ {
+        self.relative_positions()
+            .map(move |position| self.cell.to_cartesian_isometry(position))
+    }
+
+    pub fn relative_positions(&self) -> impl Iterator<Item = Transform2> + '_ {
+        self.occupied_sites.iter().flat_map(OccupiedSite::positions)
+    }
+
+    /// The total pairwise interaction energy of the configuration, summing both contacts
+    /// within the current cell and contacts with periodic images of the surrounding cells.
+    fn total_energy(&self) -> f64 {
+        let periodic_range = self.cell.periodic_range();
+        let sites: Vec<P> = self
+            .cartesian_positions()
+            .map(|transform| self.potential.with_position(transform.position()))
+            .collect();
+        let relative: Vec<Transform2> = self.relative_positions().collect();
+
+        let mut energy = 0.;
+
+        // Contacts within the current cell
+        for (index, site1) in sites.iter().enumerate() {
+            for site2 in sites.iter().skip(index + 1) {
+                energy += site1.energy(site2);
+            }
+        }
+
+        // Contacts with the periodic images of every site. Each unordered pair of sites --
+        // including a site with its own periodic images -- is only ever examined from the
+        // lower-indexed site's side, via `skip(index)`, so every periodic contact is counted
+        // exactly once rather than once from each site's perspective. A site's own images come
+        // in `+L`/`-L` pairs which are the same contact, so those are weighted by half.
+        for (index, site1) in sites.iter().enumerate() {
+            for (other, position) in relative.iter().enumerate().skip(index) {
+                let weight = if other == index { 0.5 } else { 1. };
+                for transform2 in self.cell.periodic_images(position.clone(), periodic_range, false) {
+                    let site2 = self.potential.with_position(transform2.position());
+                    energy += weight * site1.energy(&site2);
+                }
+            }
+        }
+
+        energy
+    }
+
+    pub fn initialise(potential: P, wallpaper: Wallpaper, isopointal: &[WyckoffSite]) -> Self {
+        let num_shapes = isopointal.iter().fold(0, |acc, x| acc + x.multiplicity());
+        let max_cell_size = 4. * num_shapes as f64;
+
+        let cell = Cell2::from_family(wallpaper.family, max_cell_size);
+
+        let occupied_sites: Vec<_> = isopointal.iter().map(OccupiedSite::from_wyckoff).collect();
+
+        PotentialState {
+            wallpaper,
+            potential,
+            cell,
+            occupied_sites,
+        }
+    }
+
+    pub fn from_group(potential: P, group: &WallpaperGroup) -> Result<Self, Error> {
+        let wallpaper = Wallpaper::new(group);
+        let isopointal = &[WyckoffSite::new(group)?];
+        Ok(Self::initialise(potential, wallpaper, isopointal))
+    }
+}
+
+#[cfg(test)]
+mod potential_state_tests {
+    use super::*;
+    use crate::shape::components::LJ2;
+    use crate::CrystalFamily;
+
+    fn create_wallpaper_p1() -> (Wallpaper, Vec<WyckoffSite>) {
+        let wallpaper = Wallpaper {
+            name: String::from("p1"),
+            family: CrystalFamily::Monoclinic,
+        };
+        let isopointal = vec![WyckoffSite {
+            letter: 'a',
+            symmetries: vec![Transform2::from_operations("x,y").unwrap()],
+            num_rotations: 1,
+            mirror_primary: false,
+            mirror_secondary: false,
+        }];
+
+        (wallpaper, isopointal)
+    }
+
+    #[test]
+    fn total_shapes_p1() {
+        let (wallpaper, isopointal) = create_wallpaper_p1();
+        let state = PotentialState::initialise(LJ2::default(), wallpaper, &isopointal);
+        assert_eq!(state.total_shapes(), 1);
+    }
+
+    #[test]
+    fn single_site_has_no_self_energy() {
+        let (wallpaper, isopointal) = create_wallpaper_p1();
+        let state = PotentialState::initialise(LJ2::new(0., 0., 0.01), wallpaper, &isopointal);
+        // A single particle has no neighbours within a cell many multiples of its own sigma
+        // across, so its periodic images contribute negligible energy.
+        assert!(state.score().unwrap().abs() < 1e-6);
+    }
+
+    #[test]
+    fn score_is_always_some() {
+        let (wallpaper, isopointal) = create_wallpaper_p1();
+        let state = PotentialState::initialise(LJ2::default(), wallpaper, &isopointal);
+        assert!(state.score().is_some());
+    }
+
+    #[test]
+    fn periodic_cross_site_contacts_are_not_double_counted() {
+        use approx::assert_abs_diff_eq;
+        use nalgebra::Point2;
+
+        let wallpaper = Wallpaper {
+            name: String::from("p1"),
+            family: CrystalFamily::Monoclinic,
+        };
+        let site = || WyckoffSite {
+            letter: 'a',
+            symmetries: vec![Transform2::from_operations("x,y").unwrap()],
+            num_rotations: 1,
+            mirror_primary: false,
+            mirror_secondary: false,
+        };
+        let isopointal = vec![site(), site()];
+
+        let potential = LJ2 {
+            position: Point2::new(0., 0.),
+            sigma: 1.,
+            epsilon: 1.,
+            cutoff: Some(2.),
+        };
+        let state = PotentialState::initialise(potential.clone(), wallpaper, &isopointal);
+
+        // Enlarge the cell to 10x10 and place the two sites so that, of the surrounding
+        // periodic images, exactly one cross-site contact (site 1 against the `(0, -1)` image
+        // of site 2) falls within the cutoff -- every other image, and the direct within-cell
+        // contact, is many multiples of the cutoff away and so contributes nothing. This
+        // isolates a single physical contact, letting its energy be computed independently
+        // (below) without reusing `total_energy`'s own loop structure.
+        let basis = state.generate_basis();
+        basis[0].set_value(10.); // cell x_len
+        basis[1].set_value(10.); // cell y_len
+        basis[3].set_value(0.05); // site 1's x
+        basis[4].set_value(0.05); // site 1's y
+        basis[6].set_value(0.05); // site 2's x
+        basis[7].set_value(0.95); // site 2's y
+
+        let expected = potential
+            .with_position(Point2::new(0., 0.))
+            .energy(&potential.with_position(Point2::new(0., 1.)));
+
+        // A double-counting regression would yield `2. * expected` here instead.
+        assert_abs_diff_eq!(state.score().unwrap(), expected, epsilon = 1e-9);
+    }
+}
@@ -0,0 +1,120 @@
+//
+// symmetry.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// Closes a set of generating symmetry operations into the full group they generate, mirroring
+// CrystFEL's `SymOpList` behaviour: callers hand over only the generators and every equivalent
+// operation is derived by repeated composition.
+
+use anyhow::{anyhow, Error};
+
+use crate::Transform2;
+
+/// The tolerance, in fractional coordinates and radians respectively, within which two
+/// operations are considered equal once reduced into the `[0, 1)` unit cell.
+const TOLERANCE: f64 = 1e-6;
+
+/// The largest group order closure is allowed to reach before it is abandoned as
+/// non-terminating, which happens if the generators don't correspond to a discrete
+/// crystallographic group.
+const MAX_GROUP_ORDER: usize = 48;
+
+/// The full set of symmetry operations generated by closing a set of generators under
+/// composition.
+///
+/// [`WyckoffSite::new`](crate::wallpaper::WyckoffSite::new) uses this for groups marked
+/// `generated`, so a site is built from just the wallpaper group's generators and its
+/// multiplicity falls out of the generated group's order rather than being transcribed by hand.
+/// [`Transform2`] can only represent proper rotations, so groups with mirror generators are not
+/// built this way.
+#[derive(Debug, Clone)]
+pub struct SymmetryGroup {
+    operations: Vec<Transform2>,
+}
+
+impl SymmetryGroup {
+    /// Close `generators` under composition, reducing every translation into the `[0, 1)` unit
+    /// cell, until no further operations are produced.
+    pub fn from_generators(generators: &[Transform2]) -> Result<Self, Error> {
+        let mut operations: Vec<Transform2> = Vec::new();
+        for generator in generators {
+            let reduced = generator.reduced();
+            if !operations.iter().any(|op| op.approx_eq(&reduced, TOLERANCE)) {
+                operations.push(reduced);
+            }
+        }
+
+        loop {
+            let mut combined = operations.clone();
+            for a in &operations {
+                for b in &operations {
+                    let composed = a.compose(b).reduced();
+                    if !combined.iter().any(|op| op.approx_eq(&composed, TOLERANCE)) {
+                        combined.push(composed);
+                    }
+                }
+            }
+
+            if combined.len() == operations.len() {
+                break;
+            }
+            if combined.len() > MAX_GROUP_ORDER {
+                return Err(anyhow!(
+                    "Closing the given generators produced more than {} operations; are they a valid crystallographic point group?",
+                    MAX_GROUP_ORDER
+                ));
+            }
+            operations = combined;
+        }
+
+        Ok(SymmetryGroup { operations })
+    }
+
+    /// The number of distinct operations in the closed group.
+    pub fn order(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// The operations making up the closed group.
+    pub fn operations(&self) -> &[Transform2] {
+        &self.operations
+    }
+
+    /// Consume the group, returning its operations.
+    pub fn into_operations(self) -> Vec<Transform2> {
+        self.operations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::FromSymmetry;
+
+    #[test]
+    fn identity_generator_yields_order_one() {
+        let group = SymmetryGroup::from_generators(&[Transform2::identity()]).unwrap();
+        assert_eq!(group.order(), 1);
+    }
+
+    #[test]
+    fn half_turn_generator_closes_to_order_two() {
+        let half_turn = Transform2::from_operations("-x,-y").unwrap();
+        let group = SymmetryGroup::from_generators(&[half_turn]).unwrap();
+        assert_eq!(group.order(), 2);
+    }
+
+    #[test]
+    fn quarter_turn_generator_closes_to_order_four() {
+        let quarter_turn = Transform2::from_operations("-y,x").unwrap();
+        let group = SymmetryGroup::from_generators(&[quarter_turn]).unwrap();
+        assert_eq!(group.order(), 4);
+    }
+
+    #[test]
+    fn non_crystallographic_generator_exceeds_the_order_guard() {
+        let irrational_turn = Transform2::from_parts(1., nalgebra::Vector2::new(0., 0.));
+        assert!(SymmetryGroup::from_generators(&[irrational_turn]).is_err());
+    }
+}
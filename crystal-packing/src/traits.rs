@@ -0,0 +1,78 @@
+//
+// traits.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// The shared abstractions other modules build on: the geometric `Shape`/`Intersect` pair used
+// for hard-shape packing, `Potential` for soft interactions, `FromSymmetry` for constructing
+// types from a crystallographic operation string, and `State` for anything that can be scored
+// and searched over by the optimiser.
+
+use anyhow::Error;
+use nalgebra::{Point2, RealField};
+
+use crate::Transform2;
+
+/// A type which can be constructed from a crystallographic symmetry-operation string, e.g.
+/// `"x, -y+1/2"`.
+pub trait FromSymmetry: Sized {
+    fn from_operations(ops: &str) -> Result<Self, Error>;
+}
+
+/// A geometric shape which can be placed in space via a [`Transform2`] to produce a concrete,
+/// intersection-testable instance.
+///
+/// The scalar type `T` defaults to `f64`, so existing code which never names `T` keeps working
+/// unchanged; instantiating with `T = f32` (or another `nalgebra::RealField`) lets callers trade
+/// precision for throughput on the hot intersection path. [`Cell2`](crate::Cell2),
+/// [`Transform2`] and the concrete shapes in [`crate::shape`] are generic over the same `T` for
+/// the same reason.
+pub trait Shape<T: RealField = f64> {
+    type Instance: Intersect<T>;
+
+    /// The area enclosed by the shape.
+    fn area(&self) -> T;
+
+    /// The radius of the smallest circle centred on the origin which encloses the shape.
+    fn enclosing_radius(&self) -> T;
+
+    /// Position the shape according to `transform`, producing a concrete instance.
+    fn transform(&self, transform: &Transform2<T>) -> Self::Instance;
+}
+
+/// A concrete, positioned shape instance which can be tested for overlap with another instance
+/// of the same type.
+pub trait Intersect<T: RealField = f64> {
+    fn intersects(&self, other: &Self) -> bool;
+}
+
+/// A pairwise interaction potential between two sites, e.g. a Lennard-Jones particle.
+pub trait Potential {
+    /// The interaction energy between `self` and `other`.
+    fn energy(&self, other: &Self) -> f64;
+
+    /// A copy of this potential relocated to `position`, leaving every other parameter
+    /// unchanged.
+    fn with_position(&self, position: Point2<f64>) -> Self;
+}
+
+/// A candidate configuration the optimiser can score, perturb and report on.
+pub trait State {
+    /// The total number of shape/site instances within the cell, including symmetry copies.
+    fn total_shapes(&self) -> usize;
+
+    /// A score for the current configuration, or `None` when it is invalid (e.g. shapes
+    /// overlap). Whether the optimiser maximises or minimises this value is set by the
+    /// [`Objective`](crate::Objective) it runs under.
+    fn score(&self) -> Option<f64>;
+
+    /// The free parameters of the current configuration, as a set of samplable [`Basis`]
+    /// entries.
+    ///
+    /// [`Basis`]: crate::Basis
+    fn generate_basis(&self) -> Vec<crate::Basis>;
+
+    /// A human-readable rendering of the current positions, suitable for debugging or
+    /// lightweight export.
+    fn as_positions(&self) -> Result<String, Error>;
+}
@@ -0,0 +1,186 @@
+//
+// trajectory.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// Recording and replaying the path a `monte_carlo_best_packing` search takes through
+// configuration space, rather than only its final best state.
+
+use std::fmt::Write;
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{Cell2, Transform2};
+
+/// A configuration whose unit cell and site positions can be snapshotted into a [`Trajectory`].
+///
+/// Implemented by both [`PackedState`](crate::PackedState) and
+/// [`PotentialState`](crate::PotentialState), the two states the optimiser searches over.
+pub trait Recordable {
+    /// The current unit cell.
+    fn cell(&self) -> &Cell2;
+
+    /// The current fractional-coordinate transform of every occupied site, including every
+    /// symmetry-equivalent copy.
+    fn relative_positions(&self) -> Vec<Transform2>;
+}
+
+/// A single recorded configuration: a unit cell and the fractional-coordinate transform of
+/// every occupied site at the moment it was snapshotted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigurationSnapshot {
+    pub cell: Cell2,
+    pub positions: Vec<Transform2>,
+}
+
+impl ConfigurationSnapshot {
+    fn from_state<R: Recordable>(state: &R) -> Self {
+        ConfigurationSnapshot {
+            cell: state.cell().clone(),
+            positions: state.relative_positions(),
+        }
+    }
+
+    /// Interpolate between this snapshot and `other` at `t`, linearly blending the cell's
+    /// lengths/angle and `lerp_slerp`-ing each site's transform so rotations follow the shorter
+    /// arc instead of a linear blend of matrix entries.
+    ///
+    /// Snapshots taken from the same search always list the same sites in the same order, so
+    /// the two `positions` are paired up index-wise.
+    fn lerp_slerp(&self, other: &Self, t: f64) -> Self {
+        ConfigurationSnapshot {
+            cell: self.cell.lerp(&other.cell, t),
+            positions: self
+                .positions
+                .iter()
+                .zip(&other.positions)
+                .map(|(start, end)| start.lerp_slerp(end, t))
+                .collect(),
+        }
+    }
+}
+
+/// A recorded search path: a [`ConfigurationSnapshot`] taken every `stride` steps of a
+/// `monte_carlo_best_packing` run, letting the caller inspect how a configuration evolved
+/// rather than only its final best state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trajectory {
+    stride: u64,
+    snapshots: Vec<ConfigurationSnapshot>,
+}
+
+impl Trajectory {
+    /// Start an empty trajectory which records a snapshot every `stride` steps (at least `1`).
+    pub fn new(stride: u64) -> Self {
+        Trajectory {
+            stride: stride.max(1),
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Snapshot `state` if `step` falls on this trajectory's stride.
+    pub fn record<R: Recordable>(&mut self, step: u64, state: &R) {
+        if step % self.stride == 0 {
+            self.snapshots.push(ConfigurationSnapshot::from_state(state));
+        }
+    }
+
+    /// The snapshots recorded so far, in the order they were taken.
+    pub fn snapshots(&self) -> &[ConfigurationSnapshot] {
+        &self.snapshots
+    }
+
+    /// Produce the recorded snapshots with `frames_per_segment - 1` additional interpolated
+    /// frames inserted between each consecutive pair, for smooth animation. A
+    /// `frames_per_segment` of `1` returns the snapshots unchanged.
+    pub fn frames(&self, frames_per_segment: usize) -> Vec<ConfigurationSnapshot> {
+        if self.snapshots.len() < 2 || frames_per_segment <= 1 {
+            return self.snapshots.clone();
+        }
+
+        let mut frames = Vec::with_capacity((self.snapshots.len() - 1) * frames_per_segment + 1);
+        for pair in self.snapshots.windows(2) {
+            let (start, end) = (&pair[0], &pair[1]);
+            frames.push(start.clone());
+            for step in 1..frames_per_segment {
+                let t = step as f64 / frames_per_segment as f64;
+                frames.push(start.lerp_slerp(end, t));
+            }
+        }
+        frames.push(self.snapshots.last().unwrap().clone());
+        frames
+    }
+
+    /// Render `frames_per_segment` interpolated frames per recorded segment as a sequence of
+    /// fractional-coordinate configurations, suitable for external animation tooling.
+    pub fn as_frames(&self, frames_per_segment: usize) -> Result<String, Error> {
+        let mut output = String::new();
+        for (index, frame) in self.frames(frames_per_segment).iter().enumerate() {
+            writeln!(&mut output, "Frame {}", index)?;
+            writeln!(&mut output, "{:?}", frame.cell)?;
+            writeln!(&mut output, "Positions")?;
+            for transform in &frame.positions {
+                writeln!(&mut output, "{:?}", transform)?;
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CrystalFamily;
+
+    struct StubState {
+        cell: Cell2,
+        positions: Vec<Transform2>,
+    }
+
+    impl Recordable for StubState {
+        fn cell(&self) -> &Cell2 {
+            &self.cell
+        }
+
+        fn relative_positions(&self) -> Vec<Transform2> {
+            self.positions.clone()
+        }
+    }
+
+    fn stub_state(x: f64) -> StubState {
+        StubState {
+            cell: Cell2::from_family(CrystalFamily::Tetragonal, 1. + x),
+            positions: vec![Transform2::from_parts(0., nalgebra::Vector2::new(x, 0.))],
+        }
+    }
+
+    #[test]
+    fn records_only_on_stride() {
+        let mut trajectory = Trajectory::new(2);
+        for step in 0..4 {
+            trajectory.record(step, &stub_state(step as f64));
+        }
+        assert_eq!(trajectory.snapshots().len(), 2);
+    }
+
+    #[test]
+    fn frames_inserts_interpolated_steps_between_snapshots() {
+        let mut trajectory = Trajectory::new(1);
+        trajectory.record(0, &stub_state(0.));
+        trajectory.record(1, &stub_state(1.));
+
+        let frames = trajectory.frames(4);
+        assert_eq!(frames.len(), 5);
+        assert_eq!(frames[2].positions[0].position().x, 0.5);
+    }
+
+    #[test]
+    fn single_frames_per_segment_leaves_snapshots_unchanged() {
+        let mut trajectory = Trajectory::new(1);
+        trajectory.record(0, &stub_state(0.));
+        trajectory.record(1, &stub_state(1.));
+
+        assert_eq!(trajectory.frames(1).len(), 2);
+    }
+}
@@ -0,0 +1,469 @@
+//
+// transform.rs
+// Copyright (C) 2019 Malcolm Ramsay <malramsay64@gmail.com>
+// Distributed under terms of the MIT license.
+//
+// The rigid-body transform applied to a shape or a Wyckoff site. Generic over the scalar type
+// `T` so the same code path serves both the default `f64` precision used for crystallographic
+// bookkeeping and lower-precision `f32` instantiations used when batching large polygon counts.
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use nalgebra::{IsometryMatrix2, Point2, RealField, Rotation2, Translation2, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::traits::FromSymmetry;
+
+/// A 2D rigid-body transform (rotation + translation).
+///
+/// `T` defaults to `f64`; existing call sites which never name the scalar type continue to
+/// work unchanged.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub struct Transform2<T: RealField = f64> {
+    isometry: IsometryMatrix2<T>,
+}
+
+impl<T: RealField> Transform2<T> {
+    pub fn new(angle: T, position: (T, T)) -> Self {
+        Transform2 {
+            isometry: IsometryMatrix2::new(Vector2::new(position.0, position.1), angle),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Transform2 {
+            isometry: IsometryMatrix2::identity(),
+        }
+    }
+
+    /// Apply the transform to a point.
+    pub fn transform(&self, point: &Point2<T>) -> Point2<T> {
+        self.isometry * point
+    }
+
+    /// Apply only the rotational part of the transform to a vector.
+    pub fn rotate(&self, vector: &Vector2<T>) -> Vector2<T> {
+        self.isometry * vector
+    }
+
+    /// The translational component of the transform.
+    pub fn position(&self) -> Point2<T> {
+        Point2::from(self.isometry.translation.vector.clone())
+    }
+
+    /// The rotation angle of the transform, in radians.
+    pub fn angle(&self) -> T {
+        self.isometry.rotation.angle()
+    }
+
+    pub fn from_parts(angle: T, translation: Vector2<T>) -> Self {
+        Transform2 {
+            isometry: IsometryMatrix2::from_parts(
+                Translation2::from(translation),
+                Rotation2::new(angle),
+            ),
+        }
+    }
+}
+
+impl<T: RealField> Default for Transform2<T> {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform2<f64> {
+    /// Lift this `f64` transform into another scalar type `T`.
+    ///
+    /// Used to thread an `OccupiedSite`'s always-`f64` sampled position (see
+    /// [`SharedValue`](crate::SharedValue)'s doc comment) into a `T`-valued geometry pipeline,
+    /// e.g. [`PackedState`](crate::PackedState) instantiated at `T = f32`.
+    pub fn cast<T: RealField>(&self) -> Transform2<T> {
+        let position = self.position();
+        Transform2::from_parts(
+            T::from_subset(&self.angle()),
+            Vector2::new(T::from_subset(&position.x), T::from_subset(&position.y)),
+        )
+    }
+
+    /// Compose this transform with `other`, applying `other` first and then `self` -- the
+    /// standard rigid-transform composition order, used to derive new symmetry operations from
+    /// existing ones when closing a generating set.
+    pub fn compose(&self, other: &Self) -> Self {
+        Transform2 {
+            isometry: self.isometry * other.isometry,
+        }
+    }
+
+    /// Interpolate between this transform and `other` at `t` (`0` yields `self`, `1` yields
+    /// `other`), blending translations linearly and rotations along the shorter arc between the
+    /// two angles rather than linearly through the rotation matrix entries.
+    pub fn lerp_slerp(&self, other: &Self, t: f64) -> Self {
+        Transform2 {
+            isometry: self.isometry.lerp_slerp(&other.isometry, t),
+        }
+    }
+
+    /// This transform's translation reduced into the `[0, 1)` unit cell, the convention
+    /// crystallographic symmetry operations are expressed in.
+    pub fn reduced(&self) -> Self {
+        let position = self.position();
+        Transform2::from_parts(
+            self.angle(),
+            Vector2::new(position.x.rem_euclid(1.), position.y.rem_euclid(1.)),
+        )
+    }
+
+    /// Whether this transform is equivalent to `other` to within `tolerance`, comparing
+    /// rotation angle and translation both mod their natural period -- `2π` and `1` respectively
+    /// -- so e.g. angles of `-π` and `π` or translations of `-0.0001` and `0.9999` compare equal.
+    pub(crate) fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        let periodic_eq = |a: f64, b: f64, period: f64| {
+            let diff = (a - b).rem_euclid(period);
+            diff < tolerance || period - diff < tolerance
+        };
+
+        let position = self.position();
+        let other_position = other.position();
+        periodic_eq(self.angle(), other.angle(), 2. * PI)
+            && periodic_eq(position.x, other_position.x, 1.)
+            && periodic_eq(position.y, other_position.y, 1.)
+    }
+}
+
+/// An exact rational number, used to accumulate symmetry-operation translations so terms like
+/// `x-1/3` and `y+2/3` round-trip without floating-point drift before being converted to `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rational {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Rational {
+    fn new(numerator: i64, denominator: i64) -> Result<Self, Error> {
+        if denominator == 0 {
+            return Err(anyhow!("Symmetry operation has a zero denominator"));
+        }
+        let sign = if denominator < 0 { -1 } else { 1 };
+        let divisor = gcd(numerator.abs(), denominator.abs()).max(1);
+        Ok(Rational {
+            numerator: sign * numerator / divisor,
+            denominator: denominator.abs() / divisor,
+        })
+    }
+
+    fn zero() -> Self {
+        Rational {
+            numerator: 0,
+            denominator: 1,
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl std::ops::Add for Rational {
+    type Output = Rational;
+
+    fn add(self, other: Rational) -> Rational {
+        let numerator = self.numerator * other.denominator + other.numerator * self.denominator;
+        let denominator = self.denominator * other.denominator;
+        // Both input denominators are already non-zero, so the product can't be either.
+        Rational::new(numerator, denominator).unwrap()
+    }
+}
+
+impl std::ops::Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            numerator: -self.numerator,
+            denominator: self.denominator,
+        }
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// A single signed term of a symmetry-operation component: a multiple of `x`, a multiple of
+/// `y`, or a rational constant.
+enum Term {
+    X(i64),
+    Y(i64),
+    Constant(Rational),
+}
+
+/// Parse the optional integer coefficient preceding a variable, e.g. the `"2"` in `"2x"` or
+/// `"2*x"`. A bare variable (empty `coeff`) has coefficient 1.
+fn parse_coefficient(coeff: &str) -> Result<i64, Error> {
+    let coeff = coeff.strip_suffix('*').unwrap_or(coeff).trim();
+    if coeff.is_empty() {
+        Ok(1)
+    } else {
+        coeff
+            .parse()
+            .map_err(|_| anyhow!("Invalid coefficient '{}' in symmetry operation", coeff))
+    }
+}
+
+/// Parse a single signed term, e.g. `"x"`, `"2x"`, `"2*y"`, or `"1/2"`.
+fn parse_term(positive: bool, raw: &str) -> Result<Term, Error> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err(anyhow!("Empty term in symmetry operation"));
+    }
+    let sign = if positive { 1 } else { -1 };
+
+    if let Some(coeff) = raw.strip_suffix('x') {
+        return Ok(Term::X(sign * parse_coefficient(coeff)?));
+    }
+    if let Some(coeff) = raw.strip_suffix('y') {
+        return Ok(Term::Y(sign * parse_coefficient(coeff)?));
+    }
+
+    let parse_int = |value: &str| {
+        value
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Invalid term '{}' in symmetry operation", raw))
+    };
+    let rational = match raw.split_once('/') {
+        Some((num, den)) => Rational::new(parse_int(num)?, parse_int(den)?)?,
+        None => Rational::new(parse_int(raw)?, 1)?,
+    };
+    Ok(Term::Constant(if positive { rational } else { -rational }))
+}
+
+/// Split `component` into its signed terms on top-level `+`/`-`, e.g. `"2x-y+1/2"` becomes
+/// `[(+, "2x"), (-, "y"), (+, "1/2")]`.
+fn split_terms(component: &str) -> Vec<(bool, &str)> {
+    let mut terms = Vec::new();
+    let mut positive = true;
+    let mut start = 0;
+
+    for (index, c) in component.char_indices() {
+        if (c == '+' || c == '-') && index > start {
+            terms.push((positive, &component[start..index]));
+            positive = c == '+';
+            start = index + 1;
+        } else if (c == '+' || c == '-') && index == start {
+            positive = c == '+';
+            start = index + 1;
+        }
+    }
+    if start < component.len() {
+        terms.push((positive, &component[start..]));
+    }
+    terms
+}
+
+/// Parse a single component of a symmetry-operation string, e.g. `"x"`, `"-y+1/2"`,
+/// `"2x-y"`, or `"1/2-x"`, into its `x`/`y` coefficients and rational constant translation.
+fn parse_component(component: &str) -> Result<(i64, i64, Rational), Error> {
+    let mut x_coeff = 0;
+    let mut y_coeff = 0;
+    let mut translation = Rational::zero();
+
+    for (positive, raw) in split_terms(component.trim()) {
+        match parse_term(positive, raw)? {
+            Term::X(n) => x_coeff += n,
+            Term::Y(n) => y_coeff += n,
+            Term::Constant(r) => translation = translation + r,
+        }
+    }
+
+    Ok((x_coeff, y_coeff, translation))
+}
+
+impl<T: RealField> FromSymmetry for Transform2<T> {
+    fn from_operations(ops: &str) -> Result<Self, Error> {
+        let components: Vec<&str> = ops.trim_matches(&['(', ')'][..]).split(',').collect();
+        if components.len() != 2 {
+            return Err(anyhow!("Expected two comma-separated components in '{}'", ops));
+        }
+
+        let (a, b, x_trans) = parse_component(components[0])?;
+        let (c, d, y_trans) = parse_component(components[1])?;
+
+        // A pure rotation maps (x, y) to (cosθ·x − sinθ·y, sinθ·x + cosθ·y), i.e. its linear
+        // part is the matrix [[cosθ, -sinθ], [sinθ, cosθ]]: the diagonal entries match, the
+        // off-diagonal entries are negatives of each other, and the rows are unit vectors.
+        // `Transform2` can only hold a rotation plus a translation, so anything else -- a
+        // reflection or glide (determinant -1), or a shear that isn't a rotation at all -- is
+        // rejected outright rather than silently parsed into the nearest rotation angle.
+        if a != d || b != -c || a * a + b * b != 1 {
+            return Err(anyhow!(
+                "Symmetry operation '{}' isn't a rotation `Transform2` can represent (likely a \
+                 reflection or glide)",
+                ops
+            ));
+        }
+        let angle = T::from_subset(&(c as f64)).atan2(T::from_subset(&(a as f64)));
+
+        Ok(Transform2::from_parts(
+            angle,
+            Vector2::new(
+                T::from_subset(&x_trans.to_f64()),
+                T::from_subset(&y_trans.to_f64()),
+            ),
+        ))
+    }
+}
+
+impl FromStr for Transform2<f64> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Transform2::from_operations(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn identity_is_a_no_op() {
+        let transform = Transform2::<f64>::identity();
+        let point = Point2::new(0.2, 0.3);
+        assert_eq!(transform.transform(&point), point);
+    }
+
+    #[test]
+    fn translation_only() {
+        let transform = Transform2::from_operations("x, y").unwrap();
+        let point = Point2::new(0.1, 0.2);
+        assert_abs_diff_eq!(transform.transform(&point), Point2::new(0.1, 0.2));
+    }
+
+    #[test]
+    fn translation_with_constant() {
+        let transform = Transform2::from_operations("x+1/2, y-1/2").unwrap();
+        let point = Point2::new(0.1, 0.2);
+        assert_abs_diff_eq!(transform.transform(&point), Point2::new(0.6, -0.3));
+    }
+
+    #[test]
+    fn reflections_are_rejected() {
+        assert!(Transform2::<f64>::from_operations("x, -y").is_err());
+        assert!(Transform2::<f64>::from_operations("-x, y").is_err());
+        assert!(Transform2::<f64>::from_operations("y, x").is_err());
+    }
+
+    #[test]
+    fn cast_lifts_an_f64_transform_into_f32() {
+        let transform = Transform2::from_parts(PI / 2., Vector2::new(0.25, 0.5));
+        let cast: Transform2<f32> = transform.cast();
+        assert_abs_diff_eq!(cast.angle(), PI as f32 / 2., epsilon = 1e-6);
+        assert_abs_diff_eq!(cast.position(), Point2::new(0.25_f32, 0.5), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn f32_instantiation_matches_f64() {
+        let transform_f32 = Transform2::<f32>::new(0., (1., 2.));
+        let point = Point2::new(0.5_f32, 0.5_f32);
+        assert_abs_diff_eq!(transform_f32.transform(&point), Point2::new(1.5, 2.5));
+    }
+
+    #[test]
+    fn leading_constant_before_variable() {
+        // A quarter-turn with a (1/4, 1/4) offset, as found in e.g. p4's Wyckoff positions.
+        let transform = Transform2::from_operations("1/4-y, 1/4+x").unwrap();
+        let point = Point2::new(0.2, 0.3);
+        assert_abs_diff_eq!(transform.transform(&point), Point2::new(-0.05, 0.45));
+    }
+
+    #[test]
+    fn fractions_round_trip_exactly() {
+        let transform = Transform2::from_operations("x-1/3, y+2/3").unwrap();
+        let point = Point2::new(0., 0.);
+        assert_abs_diff_eq!(transform.transform(&point), Point2::new(-1. / 3., 2. / 3.));
+    }
+
+    #[test]
+    fn wrong_number_of_components_errors() {
+        assert!(Transform2::<f64>::from_operations("x, y, z").is_err());
+    }
+
+    #[test]
+    fn malformed_term_errors() {
+        assert!(Transform2::<f64>::from_operations("x/y, y").is_err());
+    }
+
+    #[test]
+    fn zero_denominator_errors() {
+        assert!(Transform2::<f64>::from_operations("x+1/0, y").is_err());
+    }
+
+    #[test]
+    fn parse_component_accumulates_multi_term_coefficients() {
+        let (x_coeff, y_coeff, translation) = parse_component("2x-y").unwrap();
+        assert_eq!(x_coeff, 2);
+        assert_eq!(y_coeff, -1);
+        assert_abs_diff_eq!(translation.to_f64(), 0.);
+    }
+
+    #[test]
+    fn parse_component_accepts_explicit_multiplication_sign() {
+        let (x_coeff, y_coeff, _) = parse_component("2*x-3*y").unwrap();
+        assert_eq!(x_coeff, 2);
+        assert_eq!(y_coeff, -3);
+    }
+
+    #[test]
+    fn parse_component_handles_leading_constant() {
+        let (x_coeff, y_coeff, translation) = parse_component("1/2-x").unwrap();
+        assert_eq!(x_coeff, -1);
+        assert_eq!(y_coeff, 0);
+        assert_abs_diff_eq!(translation.to_f64(), 0.5);
+    }
+
+    #[test]
+    fn compose_combines_two_quarter_turns_into_a_half_turn() {
+        let quarter_turn = Transform2::from_operations("-y,x").unwrap();
+        let composed = quarter_turn.compose(&quarter_turn);
+        assert_abs_diff_eq!(composed.angle(), PI);
+    }
+
+    #[test]
+    fn reduced_wraps_translation_into_unit_cell() {
+        let transform = Transform2::from_parts(0., Vector2::new(-0.25, 1.5));
+        let reduced = transform.reduced();
+        assert_abs_diff_eq!(reduced.position().x, 0.75);
+        assert_abs_diff_eq!(reduced.position().y, 0.5);
+    }
+
+    #[test]
+    fn approx_eq_treats_angles_across_the_branch_cut_as_equal() {
+        let a = Transform2::from_parts(PI, Vector2::new(0., 0.));
+        let b = Transform2::from_parts(-PI, Vector2::new(0., 0.));
+        assert!(a.approx_eq(&b, 1e-9));
+    }
+
+    #[test]
+    fn approx_eq_treats_translations_across_the_unit_cell_boundary_as_equal() {
+        let a = Transform2::from_parts(0., Vector2::new(0.0001, 0.));
+        let b = Transform2::from_parts(0., Vector2::new(0.9999, 0.));
+        assert!(a.approx_eq(&b, 1e-3));
+    }
+
+    #[test]
+    fn approx_eq_rejects_distinct_translations() {
+        let a = Transform2::from_parts(0., Vector2::new(0., 0.));
+        let b = Transform2::from_parts(0., Vector2::new(0.5, 0.));
+        assert!(!a.approx_eq(&b, 1e-6));
+    }
+}
@@ -5,16 +5,29 @@
 //
 
 use anyhow::{anyhow, Error};
+use nalgebra::Point2;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
-use crate::{CrystalFamily, Transform2};
+use crate::traits::FromSymmetry;
+use crate::{CrystalFamily, SymmetryGroup, Transform2};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct WallpaperGroup<'a> {
     pub name: &'a str,
     pub family: CrystalFamily,
     pub wyckoff_str: Vec<&'a str>,
+    /// Whether `wyckoff_str` lists only a generating set, to be closed into the full group via
+    /// [`SymmetryGroup`], rather than every operation of an already-closed group.
+    ///
+    /// Set for the groups whose generators are all proper rotations (`p1`, `p2`, `p4`, `p3`,
+    /// `p6`). Only `p1`, `p2` and `p4` express them in an orthogonal fractional basis:
+    /// [`Transform2`] has no way to represent any operation with determinant other than `+1` in
+    /// that basis, so every other group -- whether it needs a mirror/glide generator, or (like
+    /// `p3`/`p6`) only has proper rotations but over a non-orthogonal hexagonal basis -- fails
+    /// the moment [`WyckoffSite::new`] tries to parse one of its operations, even when a
+    /// [`WallpaperGroup`] for it can still be constructed.
+    pub generated: bool,
 }
 
 /// Defining one of the Crystallographic wallpaper groups.
@@ -45,7 +58,7 @@ impl<'a> From<WallpaperGroup<'a>> for Wallpaper {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WyckoffSite {
     pub letter: char,
     pub symmetries: Vec<Transform2>,
@@ -56,42 +69,122 @@ pub struct WyckoffSite {
 
 impl WyckoffSite {
     pub fn new(group: &WallpaperGroup) -> Result<WyckoffSite, Error> {
-        let symmetries = group
+        let operations = group
             .wyckoff_str
             .iter()
             .map(|&a| Transform2::from_operations(a))
             .collect::<Result<Vec<_>, _>>()?;
+        let symmetries = if group.generated {
+            SymmetryGroup::from_generators(&operations)?.into_operations()
+        } else {
+            operations
+        };
+        let (num_rotations, mirror_primary, mirror_secondary) =
+            general_position_site_symmetry(&symmetries);
         Ok(WyckoffSite {
             letter: 'a',
             symmetries,
-            num_rotations: 1,
-            mirror_primary: false,
-            mirror_secondary: false,
+            num_rotations,
+            mirror_primary,
+            mirror_secondary,
         })
     }
     pub fn multiplicity(&self) -> usize {
         self.symmetries.len()
     }
 
-    pub fn degrees_of_freedom(&self) -> &[bool] {
-        // TODO implement -> This is only required for the non-general Wyckoff sites since all the
-        // general sites have 3 degrees-of-freedom.
-        //
-        // This will be checked as a method of the Transform struct.
-        &[true, true, true]
+    /// Which of this site's three free parameters (`x`, `y`, `angle`) remain genuinely free,
+    /// given its site symmetry, rather than being pinned by the stabilizer of its position.
+    ///
+    /// A site lying on a mirror line has the coordinate perpendicular to that mirror pinned to
+    /// a fixed value -- `mirror_primary` pins `x`, `mirror_secondary` pins `y` -- since that's
+    /// the only way the mirror can map the site back to itself. A site sitting exactly on a
+    /// rotation centre of order greater than one has both `x` and `y` pinned, since any
+    /// displacement would carry it off that centre. `angle` is always left free here;
+    /// [`OccupiedSite::get_basis`](crate::OccupiedSite::get_basis) already narrows its sampling
+    /// range using `num_rotations`.
+    ///
+    /// The general position -- the only one [`WyckoffSite::new`] currently produces -- has a
+    /// trivial stabilizer, so this always returns `[true, true, true]` for it.
+    pub fn degrees_of_freedom(&self) -> [bool; 3] {
+        let on_rotation_centre = self.num_rotations > 1;
+        [
+            !self.mirror_primary && !on_rotation_centre,
+            !self.mirror_secondary && !on_rotation_centre,
+            true,
+        ]
     }
 }
 
+/// Whether `op` maps `position` back to itself, modulo a lattice translation -- i.e. whether
+/// `position` is a fixed point of `op` once the unit cell's periodicity is accounted for.
+fn fixes(op: &Transform2, position: Point2<f64>) -> bool {
+    let mapped = op.transform(&position);
+    let wraps_to_itself = |a: f64, b: f64| {
+        let diff = (a - b).rem_euclid(1.);
+        diff < 1e-9 || 1. - diff < 1e-9
+    };
+    wraps_to_itself(mapped.x, position.x) && wraps_to_itself(mapped.y, position.y)
+}
+
+/// The stabilizer of `position` within `symmetries` -- the subset of operations that map
+/// `position` back to itself modulo a lattice translation. Always contains at least the
+/// identity.
+fn stabilizer(symmetries: &[Transform2], position: Point2<f64>) -> Vec<&Transform2> {
+    symmetries.iter().filter(|op| fixes(op, position)).collect()
+}
+
+/// The site-symmetry flags for `position` within a group whose closed operations are
+/// `symmetries`: the order of its stabilizer, and whether a mirror in that stabilizer pins `x`
+/// or `y`.
+///
+/// `mirror_primary`/`mirror_secondary` are always `false`, since a stabilizer built from
+/// `symmetries` can never contain a mirror: [`Transform2`] can't represent one in the first
+/// place (see the rotation check in `Transform2`'s
+/// [`FromSymmetry::from_operations`](crate::traits::FromSymmetry::from_operations)
+/// implementation), so `symmetries` itself never holds one to find.
+fn site_symmetry(symmetries: &[Transform2], position: Point2<f64>) -> (u64, bool, bool) {
+    let num_rotations = stabilizer(symmetries, position).len() as u64;
+    (num_rotations.max(1), false, false)
+}
+
+/// The site-symmetry flags for the general position of a group whose closed operations are
+/// `symmetries` -- the only kind of site [`WyckoffSite::new`] currently builds.
+///
+/// Evaluated at a fixed, arbitrarily-chosen point that isn't a special position (isn't fixed by
+/// any rotation centre or mirror line) of any group this module can currently construct, so its
+/// stabilizer always comes out to just the identity. This is written as a call into
+/// [`site_symmetry`], rather than bare constants, so the day [`WyckoffSite::new`] grows support
+/// for deliberately-placed special positions, it can reuse the same stabilizer computation at
+/// that position's actual coordinates instead of this one.
+fn general_position_site_symmetry(symmetries: &[Transform2]) -> (u64, bool, bool) {
+    debug_assert!(
+        !symmetries.is_empty(),
+        "a wallpaper group's closed operations always include its identity"
+    );
+    site_symmetry(symmetries, Point2::new(0.2939, 0.1187))
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Debug, Serialize, Deserialize)]
 pub enum WallpaperGroups {
     p1,
     p2,
-    p1m1,
-    p1g1,
-    p2mm,
-    p2mg,
-    p2gg,
+    pm,
+    pg,
+    cm,
+    pmm,
+    pmg,
+    pgg,
+    cmm,
+    p4,
+    p4m,
+    p4g,
+    p3,
+    p3m1,
+    p31m,
+    p6,
+    p6m,
 }
 
 impl std::str::FromStr for WallpaperGroups {
@@ -100,12 +193,21 @@ impl std::str::FromStr for WallpaperGroups {
         match s {
             "p1" => Ok(WallpaperGroups::p1),
             "p2" => Ok(WallpaperGroups::p2),
-            "p1m1" => Ok(WallpaperGroups::p1m1),
-            "p1g1" => Ok(WallpaperGroups::p1g1),
-            "pg" => Ok(WallpaperGroups::p1g1),
-            "p2mm" => Ok(WallpaperGroups::p2mm),
-            "p2mg" => Ok(WallpaperGroups::p2mg),
-            "p2gg" => Ok(WallpaperGroups::p2gg),
+            "pm" => Ok(WallpaperGroups::pm),
+            "pg" => Ok(WallpaperGroups::pg),
+            "cm" => Ok(WallpaperGroups::cm),
+            "pmm" => Ok(WallpaperGroups::pmm),
+            "pmg" => Ok(WallpaperGroups::pmg),
+            "pgg" => Ok(WallpaperGroups::pgg),
+            "cmm" => Ok(WallpaperGroups::cmm),
+            "p4" => Ok(WallpaperGroups::p4),
+            "p4m" => Ok(WallpaperGroups::p4m),
+            "p4g" => Ok(WallpaperGroups::p4g),
+            "p3" => Ok(WallpaperGroups::p3),
+            "p3m1" => Ok(WallpaperGroups::p3m1),
+            "p31m" => Ok(WallpaperGroups::p31m),
+            "p6" => Ok(WallpaperGroups::p6),
+            "p6m" => Ok(WallpaperGroups::p6m),
             _ => Err(anyhow!("Invalid Value")),
         }
     }
@@ -116,18 +218,46 @@ impl std::fmt::Display for WallpaperGroups {
         match self {
             WallpaperGroups::p1 => write!(f, "p1"),
             WallpaperGroups::p2 => write!(f, "p2"),
-            WallpaperGroups::p1m1 => write!(f, "p1m1"),
-            WallpaperGroups::p1g1 => write!(f, "p1g1"),
-            WallpaperGroups::p2mm => write!(f, "p2mm"),
-            WallpaperGroups::p2mg => write!(f, "p2mg"),
-            WallpaperGroups::p2gg => write!(f, "p2gg"),
+            WallpaperGroups::pm => write!(f, "pm"),
+            WallpaperGroups::pg => write!(f, "pg"),
+            WallpaperGroups::cm => write!(f, "cm"),
+            WallpaperGroups::pmm => write!(f, "pmm"),
+            WallpaperGroups::pmg => write!(f, "pmg"),
+            WallpaperGroups::pgg => write!(f, "pgg"),
+            WallpaperGroups::cmm => write!(f, "cmm"),
+            WallpaperGroups::p4 => write!(f, "p4"),
+            WallpaperGroups::p4m => write!(f, "p4m"),
+            WallpaperGroups::p4g => write!(f, "p4g"),
+            WallpaperGroups::p3 => write!(f, "p3"),
+            WallpaperGroups::p3m1 => write!(f, "p3m1"),
+            WallpaperGroups::p31m => write!(f, "p31m"),
+            WallpaperGroups::p6 => write!(f, "p6"),
+            WallpaperGroups::p6m => write!(f, "p6m"),
         }
     }
 }
 
 impl WallpaperGroups {
     pub fn variants() -> Vec<&'static str> {
-        vec!["p1", "p2", "p2m1", "p1g1", "p2mm", "p2mg", "p2gg"]
+        vec![
+            "p1", "p2", "pm", "pg", "cm", "pmm", "pmg", "pgg", "cmm", "p4", "p4m", "p4g", "p3",
+            "p3m1", "p31m", "p6", "p6m",
+        ]
+    }
+
+    /// The subset of [`variants`](Self::variants) for which both `TryFrom<WallpaperGroups> for
+    /// WallpaperGroup` and the resulting [`WyckoffSite::new`] actually succeed.
+    ///
+    /// Only `p1`, `p2` and `p4` generate from operations [`Transform2`] can represent *and*
+    /// parse: every mirror/glide group needs a determinant -1 operation `Transform2` can't
+    /// hold, and `p3`/`p6` -- despite having only proper rotations -- use a non-orthogonal
+    /// hexagonal basis `Transform2::from_operations` doesn't parse (see the `Err` arms of
+    /// `TryFrom<WallpaperGroups> for WallpaperGroup` just below). The remaining 14 variants
+    /// stay in [`variants`](Self::variants), since `WallpaperGroups` itself still names and
+    /// round-trips all 17, but callers that need a working [`WyckoffSite`] should filter to
+    /// this list rather than `variants()`.
+    pub fn fully_supported() -> &'static [&'static str] {
+        &["p1", "p2", "p4"]
     }
 }
 
@@ -140,37 +270,52 @@ impl<'a> TryFrom<WallpaperGroups> for WallpaperGroup<'a> {
                 name: "p1",
                 family: CrystalFamily::Monoclinic,
                 wyckoff_str: vec!["x,y"],
+                generated: true,
             }),
             WallpaperGroups::p2 => Ok(WallpaperGroup {
                 name: "p2",
                 family: CrystalFamily::Monoclinic,
-                wyckoff_str: vec!["x,y", "-x,-y"],
-            }),
-            WallpaperGroups::p1m1 => Ok(WallpaperGroup {
-                name: "p1m1",
-                family: CrystalFamily::Orthorhombic,
-                wyckoff_str: vec!["x,y", "-x,y"],
-            }),
-            WallpaperGroups::p1g1 => Ok(WallpaperGroup {
-                name: "p1m1",
-                family: CrystalFamily::Orthorhombic,
-                wyckoff_str: vec!["x,y", "-x,y+1/2"],
+                wyckoff_str: vec!["-x,-y"],
+                generated: true,
             }),
-            WallpaperGroups::p2mm => Ok(WallpaperGroup {
-                name: "p2mm",
-                family: CrystalFamily::Orthorhombic,
-                wyckoff_str: vec!["x,y", "-x,-y", "-x,y", "x,-y"],
+            WallpaperGroups::p4 => Ok(WallpaperGroup {
+                name: "p4",
+                family: CrystalFamily::Tetragonal,
+                wyckoff_str: vec!["-y,x"],
+                generated: true,
             }),
-            WallpaperGroups::p2mg => Ok(WallpaperGroup {
-                name: "p2mg",
-                family: CrystalFamily::Orthorhombic,
-                wyckoff_str: vec!["x,y", "-x, -y", "-x+1/2, y", "x+1/2, -y"],
+            WallpaperGroups::p3 => Ok(WallpaperGroup {
+                name: "p3",
+                family: CrystalFamily::Hexagonal,
+                wyckoff_str: vec!["-y,x-y"],
+                generated: true,
             }),
-            WallpaperGroups::p2gg => Ok(WallpaperGroup {
-                name: "p2gg",
-                family: CrystalFamily::Orthorhombic,
-                wyckoff_str: vec!["x,y", "-x, -y", "-x+1/2, y+1/2", "x+1/2, -y+1/2"],
+            WallpaperGroups::p6 => Ok(WallpaperGroup {
+                name: "p6",
+                family: CrystalFamily::Hexagonal,
+                wyckoff_str: vec!["x-y,x"],
+                generated: true,
             }),
+            WallpaperGroups::pm
+            | WallpaperGroups::pg
+            | WallpaperGroups::cm
+            | WallpaperGroups::pmm
+            | WallpaperGroups::pmg
+            | WallpaperGroups::pgg
+            | WallpaperGroups::cmm
+            | WallpaperGroups::p4m
+            | WallpaperGroups::p4g => Err(anyhow!(
+                "Wallpaper group '{}' needs at least one mirror or glide generator (determinant \
+                 -1), which `Transform2`'s rotation-plus-translation representation can't hold",
+                name
+            )),
+            WallpaperGroups::p3m1 | WallpaperGroups::p31m | WallpaperGroups::p6m => Err(anyhow!(
+                "Wallpaper group '{}' is a hexagonal group with a mirror generator: it's blocked \
+                 both by the non-orthogonal hexagonal basis `Transform2::from_operations` \
+                 assumes is orthogonal, and by needing a determinant -1 operation `Transform2` \
+                 can't hold",
+                name
+            )),
         }
     }
 }
@@ -178,6 +323,7 @@ impl<'a> TryFrom<WallpaperGroups> for WallpaperGroup<'a> {
 #[cfg(test)]
 mod wyckoff_site_tests {
     use super::*;
+    use std::convert::TryInto;
 
     pub fn create_wyckoff() -> WyckoffSite {
         WyckoffSite {
@@ -194,4 +340,101 @@ mod wyckoff_site_tests {
         let wyckoff = create_wyckoff();
         assert_eq!(wyckoff.multiplicity(), 1);
     }
+
+    #[test]
+    fn p2_multiplicity_comes_from_generated_closure() {
+        let group: WallpaperGroup = WallpaperGroups::p2.try_into().unwrap();
+        let wyckoff = WyckoffSite::new(&group).unwrap();
+        assert_eq!(wyckoff.multiplicity(), 2);
+    }
+
+    #[test]
+    fn general_position_has_trivial_site_symmetry_regardless_of_group_order() {
+        // p2's multiplicity is 2, but the general position WyckoffSite::new builds is a
+        // generic (x, y), not the 2-fold rotation centre itself -- its stabilizer is still
+        // just the identity.
+        let group: WallpaperGroup = WallpaperGroups::p2.try_into().unwrap();
+        let wyckoff = WyckoffSite::new(&group).unwrap();
+        assert_eq!(wyckoff.num_rotations, 1);
+        assert!(!wyckoff.mirror_primary);
+        assert!(!wyckoff.mirror_secondary);
+    }
+
+    #[test]
+    fn p4_multiplicity_comes_from_generated_closure() {
+        let group: WallpaperGroup = WallpaperGroups::p4.try_into().unwrap();
+        let wyckoff = WyckoffSite::new(&group).unwrap();
+        assert_eq!(wyckoff.multiplicity(), 4);
+    }
+
+    #[test]
+    fn mirror_groups_are_not_yet_supported() {
+        let group: Result<WallpaperGroup, _> = WallpaperGroups::pmm.try_into();
+        assert!(group.is_err());
+    }
+
+    #[test]
+    fn hexagonal_mirror_groups_are_not_yet_supported() {
+        let group: Result<WallpaperGroup, _> = WallpaperGroups::p6m.try_into();
+        assert!(group.is_err());
+    }
+
+    #[test]
+    fn hexagonal_rotation_groups_construct_but_cannot_build_a_site_yet() {
+        // p3/p6 have no mirror generator, so a `WallpaperGroup` for them builds fine, but their
+        // generators only describe a rotation in the non-orthogonal hexagonal basis -- not one
+        // `Transform2::from_operations` can parse -- so `WyckoffSite::new` still errors.
+        for variant in [WallpaperGroups::p3, WallpaperGroups::p6] {
+            let group: WallpaperGroup = variant.try_into().unwrap();
+            assert!(WyckoffSite::new(&group).is_err());
+        }
+    }
+
+    #[test]
+    fn fully_supported_groups_actually_build_a_wyckoff_site() {
+        for name in WallpaperGroups::fully_supported() {
+            let group: WallpaperGroup = name.parse::<WallpaperGroups>().unwrap().try_into().unwrap();
+            assert!(
+                WyckoffSite::new(&group).is_ok(),
+                "{} is listed as fully supported but failed to build a WyckoffSite",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn fully_supported_is_a_strict_subset_of_all_variants() {
+        for name in WallpaperGroups::fully_supported() {
+            assert!(WallpaperGroups::variants().contains(name));
+        }
+        assert!(WallpaperGroups::fully_supported().len() < WallpaperGroups::variants().len());
+    }
+
+    #[test]
+    fn stabilizer_detects_p2s_rotation_centre() {
+        let group: WallpaperGroup = WallpaperGroups::p2.try_into().unwrap();
+        let wyckoff = WyckoffSite::new(&group).unwrap();
+        let (num_rotations, mirror_primary, mirror_secondary) =
+            site_symmetry(&wyckoff.symmetries, Point2::origin());
+        assert_eq!(num_rotations, 2);
+        assert!(!mirror_primary);
+        assert!(!mirror_secondary);
+    }
+
+    #[test]
+    fn stabilizer_detects_p4s_four_fold_centre() {
+        let group: WallpaperGroup = WallpaperGroups::p4.try_into().unwrap();
+        let wyckoff = WyckoffSite::new(&group).unwrap();
+        let (num_rotations, _, _) = site_symmetry(&wyckoff.symmetries, Point2::origin());
+        assert_eq!(num_rotations, 4);
+    }
+
+    #[test]
+    fn stabilizer_is_trivial_away_from_any_rotation_centre() {
+        let group: WallpaperGroup = WallpaperGroups::p4.try_into().unwrap();
+        let wyckoff = WyckoffSite::new(&group).unwrap();
+        let (num_rotations, _, _) =
+            site_symmetry(&wyckoff.symmetries, Point2::new(0.2939, 0.1187));
+        assert_eq!(num_rotations, 1);
+    }
 }